@@ -0,0 +1,625 @@
+use crate::{DefaultWeigher, InsertError, LruCache, MutateError, Weigher};
+use crate::RemovalCause;
+use crate::entry::{Entry, EntryMeta, EntryPtr};
+
+use hashbrown::raw::Bucket;
+
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+/// A view into a single entry of an [LruCache], which may either be vacant or
+/// occupied. This is obtained by calling [LruCache::entry].
+pub enum CacheEntry<'a, K, V, S, W = DefaultWeigher> {
+
+    /// An occupied entry, i.e. the cache contains a value for the key.
+    Occupied(OccupiedEntry<'a, K, V, S, W>),
+
+    /// A vacant entry, i.e. the cache contains no value for the key.
+    Vacant(VacantEntry<'a, K, V, S, W>)
+}
+
+impl<'a, K, V, S, W> CacheEntry<'a, K, V, S, W> {
+
+    /// Gets a reference to the key of this entry. For occupied entries, this
+    /// is the key stored in the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    ///
+    /// assert_eq!("apple", cache.entry("apple".to_owned()).key());
+    /// ```
+    pub fn key(&self) -> &K {
+        match self {
+            CacheEntry::Occupied(entry) => entry.key(),
+            CacheEntry::Vacant(entry) => entry.key()
+        }
+    }
+}
+
+impl<'a, K, V, S, W> CacheEntry<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+
+    /// Ensures a value is in the entry by inserting the given default if it is
+    /// vacant. A reference to the value in the entry is returned, which is
+    /// most-recently-used in any case.
+    ///
+    /// # Arguments
+    ///
+    /// * `default`: The value to insert if the entry is vacant.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError::EntryTooLarge] if the entry is vacant and the
+    /// new entry alone would already be too large to fit inside the cache's
+    /// size limit, or an [InsertError::WouldEjectPinned] if it does not fit
+    /// beside the pinned entries (see [LruCache::pin]). If this occurs, the
+    /// entry was not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert_eq!(&"sweet".to_owned(), cache.entry("apple".to_owned())
+    ///     .or_insert("sour".to_owned()).unwrap());
+    /// assert_eq!(&"sour".to_owned(), cache.entry("lemon".to_owned())
+    ///     .or_insert("sour".to_owned()).unwrap());
+    /// ```
+    pub fn or_insert(self, default: V) -> Result<&'a V, InsertError<K, V>> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the given
+    /// function if it is vacant. The function is not called if the entry is
+    /// occupied. A reference to the value in the entry is returned, which is
+    /// most-recently-used in any case.
+    ///
+    /// # Arguments
+    ///
+    /// * `default`: A function that computes the value to insert if the entry
+    ///   is vacant.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError::EntryTooLarge] if the entry is vacant and the
+    /// new entry alone would already be too large to fit inside the cache's
+    /// size limit, or an [InsertError::WouldEjectPinned] if it does not fit
+    /// beside the pinned entries (see [LruCache::pin]). If this occurs, the
+    /// entry was not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// let value = cache.entry("apple".to_owned())
+    ///     .or_insert_with(|| "sweet".to_owned())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(&"sweet".to_owned(), value);
+    /// ```
+    pub fn or_insert_with<F>(self, default: F)
+        -> Result<&'a V, InsertError<K, V>>
+    where
+        F: FnOnce() -> V
+    {
+        match self {
+            CacheEntry::Occupied(entry) => Ok(entry.into_ref()),
+            CacheEntry::Vacant(entry) => entry.insert(default())
+        }
+    }
+
+    /// Applies a mutating function to the value of this entry if it is
+    /// occupied. Vacant entries are returned unchanged.
+    ///
+    /// As with [LruCache::mutate], the size of the entry is updated after the
+    /// operation and, if necessary, least-recently-used entries are ejected to
+    /// restore the memory requirement.
+    ///
+    /// # Arguments
+    ///
+    /// * `op`: An operation that takes as input a mutable reference to the
+    ///   value and mutates it.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError::EntryTooLarge] if the operation expanded the
+    /// value so much that the entry no longer fit inside the memory limit of
+    /// the cache, or an [MutateError::WouldEjectPinned] if it no longer fit
+    /// beside the other pinned entries (see [LruCache::pin]). If that is the
+    /// case, the entry is removed and its parts returned in the error data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.entry("apple".to_owned())
+    ///     .and_modify(|s| s.push_str(" and sour"))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(&"sweet and sour".to_owned()), cache.peek("apple"));
+    /// ```
+    pub fn and_modify<F>(self, op: F)
+        -> Result<CacheEntry<'a, K, V, S, W>, MutateError<K, V>>
+    where
+        F: FnOnce(&mut V)
+    {
+        match self {
+            CacheEntry::Occupied(entry) => {
+                let entry_ptr = entry.entry_ptr();
+                unsafe {
+                    entry.cache.mutate_ptr(entry_ptr, op,
+                        LruCache::drop_ejected)?;
+                }
+                Ok(CacheEntry::Occupied(entry))
+            },
+            CacheEntry::Vacant(entry) => Ok(CacheEntry::Vacant(entry))
+        }
+    }
+}
+
+/// A view into an occupied entry of an [LruCache]. This is part of the
+/// [CacheEntry] enum.
+pub struct OccupiedEntry<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>,
+    key: K,
+    bucket: Bucket<Entry<K, V>>
+}
+
+impl<'a, K, V, S, W> OccupiedEntry<'a, K, V, S, W> {
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>, key: K,
+            bucket: Bucket<Entry<K, V>>) -> OccupiedEntry<'a, K, V, S, W> {
+        OccupiedEntry {
+            cache,
+            key,
+            bucket
+        }
+    }
+
+    fn entry_ptr(&self) -> EntryPtr<K, V> {
+        EntryPtr::new(self.bucket.as_ptr())
+    }
+
+    /// Gets a reference to the key stored in the cache for this entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let CacheEntry::Occupied(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!("apple", entry.key());
+    /// }
+    /// ```
+    pub fn key(&self) -> &K {
+        unsafe { self.bucket.as_ref().key() }
+    }
+
+    /// Gets a reference to the value of this entry.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let CacheEntry::Occupied(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!("sweet", entry.get());
+    /// }
+    /// ```
+    pub fn get(&self) -> &V {
+        unsafe { self.bucket.as_ref().value() }
+    }
+
+    /// Converts this entry into a reference to its value with a lifetime
+    /// bound to the cache itself.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let CacheEntry::Occupied(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!("sweet", entry.into_ref());
+    /// }
+    /// ```
+    pub fn into_ref(self) -> &'a V {
+        unsafe { self.entry_ptr().get_extended().value() }
+    }
+}
+
+impl<'a, K, V, S, W> OccupiedEntry<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+
+    /// Replaces the value of this entry with the given one and returns the
+    /// old value. If the new value requires more memory than the old one,
+    /// least-recently-used entries are ejected from the cache until it fits.
+    /// As with [LruCache::insert], the entry expires according to the default
+    /// time-to-live of the cache (see [LruCache::set_default_ttl]).
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: The new value to store in this entry.
+    ///
+    /// # Errors
+    ///
+    /// * Raises an [InsertError::EntryTooLarge] if the entry with the new
+    ///   value would be too large to fit inside the cache's size limit.
+    /// * Otherwise, raises an [InsertError::WouldEjectPinned] if the entry
+    ///   with the new value would not fit beside the other pinned entries (see
+    ///   [LruCache::pin]).
+    ///
+    /// If any error was raised, the cache remains unchanged, and the key used
+    /// to obtain this entry is returned in the error data along with the given
+    /// value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let CacheEntry::Occupied(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!(Ok("sweet".to_owned()), entry.insert("sour".to_owned()));
+    /// }
+    ///
+    /// assert_eq!(Some(&"sour".to_owned()), cache.peek("apple"));
+    /// ```
+    pub fn insert(self, value: V) -> Result<V, InsertError<K, V>> {
+        let max_size = self.cache.max_size;
+        let mut available_memory = self.cache.available_memory();
+        let mut entry_ptr = self.entry_ptr();
+        let index = unsafe { self.cache.index_of(entry_ptr) };
+        let pinned = self.cache.meta_at(index).pins > 0;
+        let entry = entry_ptr.get_mut();
+        let new_entry_size =
+            self.cache.weigher.weigh(unsafe { entry.key() }, &value);
+
+        if pinned {
+            available_memory += entry.size;
+        }
+
+        if new_entry_size > max_size {
+            return Err(InsertError::EntryTooLarge {
+                key: self.key,
+                value,
+                entry_size: new_entry_size,
+                max_size
+            });
+        }
+
+        if new_entry_size > available_memory {
+            return Err(InsertError::WouldEjectPinned {
+                key: self.key,
+                value,
+                entry_size: new_entry_size,
+                available_memory
+            });
+        }
+
+        let old_value = mem::replace(unsafe { entry.value_mut() }, value);
+        let old_entry_size = mem::replace(&mut entry.size, new_entry_size);
+        let meta = EntryMeta {
+            expires_at: self.cache.expiry_for(self.cache.default_ttl),
+            ..*self.cache.meta_at(index)
+        };
+        self.cache.set_meta_at(index, meta);
+        self.cache.current_size =
+            self.cache.current_size - old_entry_size + new_entry_size;
+
+        if pinned {
+            self.cache.pinned_size =
+                self.cache.pinned_size - old_entry_size + new_entry_size;
+        }
+
+        // The entry was marked as most-recently-used when this view was
+        // created and fits in the cache, so it is never ejected here.
+
+        self.cache.eject_to_target(max_size, RemovalCause::Ejected);
+        Ok(old_value)
+    }
+
+    /// Removes this entry from the cache and returns its key and value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let CacheEntry::Occupied(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!(("apple".to_owned(), "sweet".to_owned()),
+    ///         entry.remove_entry());
+    /// }
+    ///
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.cache.take_bucket(self.bucket) }
+    }
+
+    /// Removes this entry from the cache and returns its value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let CacheEntry::Occupied(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!("sweet".to_owned(), entry.remove());
+    /// }
+    ///
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+/// A view into a vacant entry of an [LruCache]. This is part of the
+/// [CacheEntry] enum.
+pub struct VacantEntry<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>,
+    key: K,
+    hash: u64
+}
+
+impl<'a, K, V, S, W> VacantEntry<'a, K, V, S, W> {
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>, key: K, hash: u64)
+            -> VacantEntry<'a, K, V, S, W> {
+        VacantEntry {
+            cache,
+            key,
+            hash
+        }
+    }
+
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through this entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    ///
+    /// if let CacheEntry::Vacant(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!("apple", entry.key());
+    /// }
+    /// ```
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key that would be used when inserting a value
+    /// through this entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    ///
+    /// if let CacheEntry::Vacant(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!("apple".to_owned(), entry.into_key());
+    /// }
+    /// ```
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<'a, K, V, S, W> VacantEntry<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+
+    /// Inserts the given value into the cache with the key of this entry. The
+    /// new entry is most-recently-used. If inserting it would violate the
+    /// memory limit, the least-recently-used values are ejected from the cache
+    /// until it fits. A reference to the inserted value is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: The value to store in the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError::EntryTooLarge] if the entry alone would already
+    /// be too large to fit inside the cache's size limit, or an
+    /// [InsertError::WouldEjectPinned] if it does not fit beside the pinned
+    /// entries (see [LruCache::pin]). If this occurs, the entry was not
+    /// inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{CacheEntry, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    ///
+    /// if let CacheEntry::Vacant(entry) = cache.entry("apple".to_owned()) {
+    ///     assert_eq!(Ok(&"sweet".to_owned()),
+    ///         entry.insert("sweet".to_owned()));
+    /// }
+    ///
+    /// assert_eq!(1, cache.len());
+    /// ```
+    pub fn insert(self, value: V) -> Result<&'a V, InsertError<K, V>> {
+        let entry = self.cache.prepare_insert(self.key, value)?;
+        let available_memory = self.cache.available_memory();
+        let entry = self.cache.check_available_memory(entry, available_memory)?;
+        self.cache.eject_to_target(self.cache.max_size - entry.size(),
+            RemovalCause::Ejected);
+        let entry_ptr = self.cache.insert_unchecked(entry, self.hash,
+            self.cache.default_ttl);
+
+        Ok(unsafe { entry_ptr.get_extended().value() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{CacheEntry, InsertError, LruCache, MutateError};
+    use crate::tests::large_test_cache;
+
+    #[test]
+    fn entry_is_occupied_iff_key_is_contained() {
+        let mut cache = large_test_cache();
+
+        assert!(matches!(cache.entry("hello"), CacheEntry::Occupied(_)));
+        assert!(matches!(cache.entry("bonjour"), CacheEntry::Vacant(_)));
+    }
+
+    #[test]
+    fn occupied_entry_is_marked_most_recently_used() {
+        let mut cache = large_test_cache();
+        let _ = cache.entry("hello");
+
+        assert_eq!(Some((&"hello", &"world")), cache.peek_mru());
+        assert_eq!(Some((&"greetings", &"moon")), cache.peek_lru());
+    }
+
+    #[test]
+    fn or_insert_with_does_not_call_function_if_occupied() {
+        let mut cache = large_test_cache();
+        let value = cache.entry("hello")
+            .or_insert_with(|| panic!("function was called"))
+            .unwrap();
+
+        assert_eq!(&"world", value);
+        assert_eq!(5, cache.len());
+    }
+
+    #[test]
+    fn or_insert_with_inserts_as_most_recently_used_if_vacant() {
+        let mut cache = large_test_cache();
+        let size_before = cache.current_size();
+        let value = cache.entry("bonjour")
+            .or_insert_with(|| "saturn")
+            .unwrap();
+
+        assert_eq!(&"saturn", value);
+        assert_eq!(6, cache.len());
+        assert!(cache.current_size() > size_before);
+        assert_eq!(Some((&"bonjour", &"saturn")), cache.peek_mru());
+    }
+
+    #[test]
+    fn vacant_insert_ejects_lru_if_overflowing() {
+        let mut cache = large_test_cache();
+        cache.set_max_size(cache.current_size());
+        cache.entry("bonjour").or_insert("saturn").unwrap();
+
+        assert_eq!(5, cache.len());
+        assert!(!cache.contains("hello"));
+        assert!(cache.contains("bonjour"));
+    }
+
+    #[test]
+    fn vacant_insert_rejects_too_large_entry() {
+        let mut cache = LruCache::new(256);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        let result = cache.entry(1).or_insert(vec![0u8; 256]);
+
+        assert!(matches!(result,
+            Err(InsertError::EntryTooLarge { key: 1, .. })));
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn and_modify_updates_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+        cache.entry(0).and_modify(|v| v.truncate(0)).unwrap();
+        cache.entry(0).and_modify(|v| v.shrink_to_fit()).unwrap();
+
+        assert_eq!(size_before - 8, cache.current_size());
+        assert_eq!(Some((&0, &vec![])), cache.peek_mru());
+    }
+
+    #[test]
+    fn and_modify_removes_too_expanded_entry() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        let result = cache.entry(0).and_modify(|v| v.resize(1024, 0));
+
+        assert!(matches!(result,
+            Err(MutateError::EntryTooLarge { key: 0, .. })));
+        assert_eq!(1, cache.len());
+        assert!(!cache.contains(&0));
+    }
+
+    #[test]
+    fn occupied_insert_replaces_value_and_updates_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+
+        if let CacheEntry::Occupied(entry) = cache.entry(0) {
+            assert_eq!(Ok(vec![0u8; 8]), entry.insert(vec![1u8; 16]));
+        }
+
+        assert_eq!(size_before + 8, cache.current_size());
+        assert_eq!(Some(&vec![1u8; 16]), cache.peek(&0));
+    }
+
+    #[test]
+    fn occupied_remove_removes_entry() {
+        let mut cache = large_test_cache();
+
+        if let CacheEntry::Occupied(entry) = cache.entry("ahoy") {
+            assert_eq!("mars", entry.remove());
+        }
+
+        assert_eq!(4, cache.len());
+        assert!(!cache.contains("ahoy"));
+        assert_eq!(4, cache.iter().count());
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::Instant;
+
+/// A source of the current time, used by an [LruCache](crate::LruCache) to
+/// determine whether entries are expired (see
+/// [LruCache::insert_with_ttl](crate::LruCache::insert_with_ttl)). By default,
+/// the [SystemClock] is used. A custom clock can be provided with
+/// [LruCache::set_clock](crate::LruCache::set_clock), which is useful to test
+/// expiration deterministically.
+///
+/// # Example
+///
+/// ```
+/// use lru_mem::Clock;
+/// use std::sync::Mutex;
+/// use std::time::{Duration, Instant};
+///
+/// struct ManualClock {
+///     now: Mutex<Instant>
+/// }
+///
+/// impl ManualClock {
+///     fn advance(&self, duration: Duration) {
+///         *self.now.lock().unwrap() += duration;
+///     }
+/// }
+///
+/// impl Clock for ManualClock {
+///     fn now(&self) -> Instant {
+///         *self.now.lock().unwrap()
+///     }
+/// }
+/// ```
+pub trait Clock: Send + Sync {
+
+    /// Gets the current point in time.
+    fn now(&self) -> Instant;
+}
+
+/// A [Clock] that reports the system time using [Instant::now]. This is the
+/// default clock of an [LruCache](crate::LruCache).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
@@ -0,0 +1,478 @@
+use crate::{DefaultWeigher, LruCache, MutateError, Weigher};
+use crate::entry::EntryPtr;
+
+use std::hash::{BuildHasher, Hash};
+
+/// A cursor over the entries of an [LruCache] which can move freely in both
+/// directions of the usage history and modify the entry it points to. This is
+/// obtained by calling [LruCache::cursor_lru] or [LruCache::cursor_mru].
+///
+/// Moving to the next entry means moving towards the most-recently-used end,
+/// moving to the previous entry means moving towards the least-recently-used
+/// end. Besides pointing to an entry, the cursor can point to a "ghost"
+/// position located between the most- and least-recently-used entry. Moving
+/// to the next entry from the most-recently-used entry or to the previous
+/// entry from the least-recently-used entry leads to this position, and
+/// moving further wraps around to the other end.
+///
+/// Moving the cursor and viewing entries does not alter the usage history,
+/// i.e. the semantics are as in [LruCache::peek].
+pub struct CursorMut<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>,
+    current: EntryPtr<K, V>
+}
+
+impl<'a, K, V, S, W> CursorMut<'a, K, V, S, W> {
+
+    /// Requires the pointer to point either to an entry contained in the given
+    /// cache or to its seal.
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>,
+            current: EntryPtr<K, V>) -> CursorMut<'a, K, V, S, W> {
+        CursorMut {
+            cache,
+            current
+        }
+    }
+
+    fn entry_at(&self, ptr: EntryPtr<K, V>) -> Option<(&K, &V)> {
+        if ptr == self.cache.seal {
+            None
+        }
+        else {
+            unsafe {
+                let entry = ptr.get_extended();
+                Some((entry.key(), entry.value()))
+            }
+        }
+    }
+
+    /// Gets references to the key and value of the entry the cursor points
+    /// to. If the cursor points to the ghost position, `None` is returned.
+    ///
+    /// The memory requirement of the key and value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_lru();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cursor.current());
+    ///
+    /// cursor.move_next();
+    ///
+    /// assert_eq!(None, cursor.current());
+    /// ```
+    pub fn current(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.current)
+    }
+
+    /// Gets references to the key and value of the entry after the one the
+    /// cursor points to, i.e. the next more recently used one. If there is no
+    /// such entry, `None` is returned. If the cursor points to the ghost
+    /// position, this is the least-recently-used entry.
+    ///
+    /// The memory requirement of the key and value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let cursor = cache.cursor_lru();
+    ///
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cursor.peek_next());
+    /// ```
+    pub fn peek_next(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.current.get().prev)
+    }
+
+    /// Gets references to the key and value of the entry before the one the
+    /// cursor points to, i.e. the next less recently used one. If there is no
+    /// such entry, `None` is returned. If the cursor points to the ghost
+    /// position, this is the most-recently-used entry.
+    ///
+    /// The memory requirement of the key and value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let cursor = cache.cursor_mru();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cursor.peek_prev());
+    /// ```
+    pub fn peek_prev(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.current.get().next)
+    }
+
+    /// Moves the cursor to the next more recently used entry. If the cursor
+    /// points to the most-recently-used entry, it is moved to the ghost
+    /// position. If it points to the ghost position, it is moved to the
+    /// least-recently-used entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_lru();
+    /// cursor.move_next();
+    ///
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cursor.current());
+    /// ```
+    pub fn move_next(&mut self) {
+        self.current = self.current.get().prev;
+    }
+
+    /// Moves the cursor to the next less recently used entry. If the cursor
+    /// points to the least-recently-used entry, it is moved to the ghost
+    /// position. If it points to the ghost position, it is moved to the
+    /// most-recently-used entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_mru();
+    /// cursor.move_prev();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cursor.current());
+    /// ```
+    pub fn move_prev(&mut self) {
+        self.current = self.current.get().next;
+    }
+}
+
+impl<'a, K, V, S, W> CursorMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+
+    /// Removes the entry the cursor points to from the cache and returns its
+    /// key and value. The cursor is moved to the next more recently used
+    /// entry. If the cursor points to the ghost position, nothing happens and
+    /// `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_lru();
+    ///
+    /// assert_eq!(Some(("apple".to_owned(), "sweet".to_owned())),
+    ///     cursor.remove_current());
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cursor.current());
+    /// ```
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        if self.current == self.cache.seal {
+            return None;
+        }
+
+        let next = self.current.get().prev;
+        let removed = unsafe { self.cache.remove_ptr(self.current) };
+        self.current = next;
+
+        Some(removed)
+    }
+
+    /// Marks the entry the cursor points to as most-recently-used, as with
+    /// [LruCache::touch]. The cursor is moved to the entry that was the next
+    /// more recently used one before, so a sweep from the least-recently-used
+    /// end can continue. If the cursor points to the ghost position, nothing
+    /// happens and `false` is returned, otherwise `true` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_lru();
+    ///
+    /// assert!(cursor.move_current_to_mru());
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cursor.current());
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.peek_mru());
+    /// ```
+    pub fn move_current_to_mru(&mut self) -> bool {
+        if self.current == self.cache.seal {
+            return false;
+        }
+
+        let next = self.current.get().prev;
+        self.cache.touch_ptr(self.current);
+        self.current = next;
+
+        true
+    }
+
+    /// Marks the entry the cursor points to as least-recently-used. The cursor
+    /// is moved to the entry that was the next more recently used one before.
+    /// If the cursor points to the ghost position, nothing happens and `false`
+    /// is returned, otherwise `true` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_mru();
+    ///
+    /// assert!(cursor.move_current_to_lru());
+    /// assert_eq!(None, cursor.current());
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cache.peek_lru());
+    /// ```
+    pub fn move_current_to_lru(&mut self) -> bool {
+        if self.current == self.cache.seal {
+            return false;
+        }
+
+        let next = self.current.get().prev;
+
+        self.cache.demote_ptr(self.current);
+        self.current = next;
+
+        true
+    }
+}
+
+impl<'a, K, V, S, W> CursorMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+
+    /// Applies a mutating function to the value of the entry the cursor
+    /// points to. As with [LruCache::mutate], the size of the entry is updated
+    /// after the operation and, if necessary, least-recently-used entries
+    /// other than the mutated one are ejected to restore the memory
+    /// requirement. In contrast to [LruCache::mutate], the usage history of
+    /// the mutated entry is not altered and the cursor keeps pointing to it.
+    /// If the cursor points to the ghost position, the function is not called
+    /// and `Ok(None)` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `op`: An operation that takes as input a mutable reference to the
+    ///   value and mutates it. Its result is returned by this method.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError::EntryTooLarge] if the operation expanded the
+    /// value so much that the entry no longer fit inside the memory limit of
+    /// the cache, or an [MutateError::WouldEjectPinned] if it no longer fit
+    /// beside the other pinned entries (see [LruCache::pin]). If that is the
+    /// case, the entry is removed and its parts returned in the error data.
+    /// The cursor is then moved to the next more recently used entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_lru();
+    /// let len = cursor.mutate_current(|s| {
+    ///     s.push_str(" and sour");
+    ///     s.len()
+    /// });
+    ///
+    /// assert_eq!(Ok(Some(14)), len);
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet and sour".to_owned())),
+    ///     cache.peek_lru());
+    /// ```
+    pub fn mutate_current<R, F>(&mut self, op: F)
+        -> Result<Option<R>, MutateError<K, V>>
+    where
+        F: FnOnce(&mut V) -> R
+    {
+        if self.current == self.cache.seal {
+            return Ok(None);
+        }
+
+        let next = self.current.get().prev;
+
+        unsafe {
+            let old_weight = self.cache.weigh_ptr(self.current);
+            let result = op(self.current.get_mut().value_mut());
+            let new_weight = self.cache.weigh_ptr(self.current);
+
+            match self.cache.resize_entry(self.current, old_weight,
+                    new_weight) {
+                Ok(expanded) => {
+                    if expanded {
+                        self.cache.eject_to_target_sparing(
+                            self.cache.max_size, self.current,
+                            LruCache::drop_ejected);
+                    }
+
+                    Ok(Some(result))
+                },
+                Err(err) => {
+                    self.current = next;
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{LruCache, MutateError};
+    use crate::tests::large_test_cache;
+
+    #[test]
+    fn cursor_on_empty_cache_points_to_ghost() {
+        let mut cache = LruCache::<u32, u32>::new(1024);
+        let mut cursor = cache.cursor_lru();
+
+        assert_eq!(None, cursor.current());
+        assert_eq!(None, cursor.peek_next());
+        assert_eq!(None, cursor.peek_prev());
+        assert_eq!(None, cursor.remove_current());
+        assert!(!cursor.move_current_to_mru());
+        assert_eq!(Ok(None), cursor.mutate_current(|_| ()));
+    }
+
+    #[test]
+    fn cursor_visits_entries_in_both_directions() {
+        let mut cache = large_test_cache();
+        let mut cursor = cache.cursor_lru();
+        let mut forward = Vec::new();
+
+        while let Some((key, _)) = cursor.current() {
+            forward.push(*key);
+            cursor.move_next();
+        }
+
+        cursor.move_prev();
+        let mut backward = Vec::new();
+
+        while let Some((key, _)) = cursor.current() {
+            backward.push(*key);
+            cursor.move_prev();
+        }
+
+        backward.reverse();
+
+        assert_eq!(cache.keys().copied().collect::<Vec<_>>(), forward);
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn cursor_does_not_alter_usage_history() {
+        let mut cache = large_test_cache();
+        let mut cursor = cache.cursor_lru();
+        cursor.move_next();
+        cursor.move_next();
+
+        assert_eq!(Some((&"ahoy", &"mars")), cursor.current());
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+        assert_eq!(Some((&"good morning", &"jupiter")), cache.peek_mru());
+    }
+
+    #[test]
+    fn removing_through_cursor_allows_sweeping() {
+        let mut cache = large_test_cache();
+        let mut cursor = cache.cursor_lru();
+
+        while let Some((key, _)) = cursor.current() {
+            if key.starts_with('h') {
+                cursor.remove_current();
+            }
+            else {
+                cursor.move_next();
+            }
+        }
+
+        assert_eq!(vec!["greetings", "ahoy", "good morning"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(3, cache.len());
+    }
+
+    #[test]
+    fn moving_current_to_mru_and_lru_reorders_entries() {
+        let mut cache = large_test_cache();
+        let mut cursor = cache.cursor_lru();
+        cursor.move_current_to_mru();
+
+        assert_eq!(Some((&"greetings", &"moon")), cursor.current());
+
+        cursor.move_next();
+        cursor.move_current_to_lru();
+
+        assert_eq!(Some((&"hi", &"venus")), cursor.current());
+        assert_eq!(vec!["ahoy", "greetings", "hi", "good morning", "hello"],
+            cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn mutating_current_ejects_other_entries_only() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.set_max_size(cache.current_size());
+        let mut cursor = cache.cursor_lru();
+        let result = cursor.mutate_current(|v| v.push(0));
+
+        assert_eq!(Ok(Some(())), result);
+        assert_eq!(Some((&0, &vec![0u8; 9])), cursor.current());
+        assert_eq!(1, cache.len());
+        assert!(cache.current_size() <= cache.max_size());
+    }
+
+    #[test]
+    fn mutating_current_removes_too_expanded_entry() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        let mut cursor = cache.cursor_lru();
+        let result = cursor.mutate_current(|v| v.resize(1024, 0));
+
+        assert!(matches!(result,
+            Err(MutateError::EntryTooLarge { key: 0, .. })));
+        assert_eq!(Some((&1, &vec![0u8; 8])), cursor.current());
+        assert_eq!(1, cache.len());
+    }
+}
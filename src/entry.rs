@@ -1,7 +1,10 @@
 use crate::MemSize;
 
+use hashbrown::raw::RawTable;
+
 use std::mem::{self, MaybeUninit};
 use std::ptr;
+use std::time::{Duration, Instant};
 
 /// Gets the memory an entry with the given key and value would occupy in an
 /// LRU cache, in bytes. This is also the function used internally, thus if the
@@ -41,19 +44,15 @@ pub(crate) struct UnhingedEntry<K, V> {
     value: V
 }
 
-impl<K: MemSize, V: MemSize> UnhingedEntry<K, V> {
-    pub(crate) fn new(key: K, value: V) -> UnhingedEntry<K, V> {
-        let size = entry_size(&key, &value);
-
+impl<K, V> UnhingedEntry<K, V> {
+    pub(crate) fn new(key: K, value: V, size: usize) -> UnhingedEntry<K, V> {
         UnhingedEntry {
             size,
             key,
             value
         }
     }
-}
 
-impl<K, V> UnhingedEntry<K, V> {
     pub(crate) fn size(&self) -> usize {
         self.size
     }
@@ -67,6 +66,77 @@ impl<K, V> UnhingedEntry<K, V> {
     }
 }
 
+/// Metadata of an entry which is stored outside of the [Entry] in a table
+/// indexed by bucket, so it does not count towards the size of the entry.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) struct EntryMeta {
+    pub(crate) pins: usize,
+    pub(crate) expires_at: Option<Instant>,
+    pub(crate) last_access: Option<Instant>,
+    pub(crate) access_stamp: u64
+}
+
+/// The metadata of every entry while the metadata table is not allocated.
+pub(crate) static NO_META: EntryMeta = EntryMeta {
+    pins: 0,
+    expires_at: None,
+    last_access: None,
+    access_stamp: 0
+};
+
+impl EntryMeta {
+    pub(crate) fn idle_deadline(&self, time_to_idle: Option<Duration>)
+            -> Option<Instant> {
+        self.last_access?.checked_add(time_to_idle?)
+    }
+
+    pub(crate) fn deadline(&self, time_to_idle: Option<Duration>)
+            -> Option<Instant> {
+        match (self.expires_at, self.idle_deadline(time_to_idle)) {
+            (Some(expires_at), Some(idle_deadline)) =>
+                Some(expires_at.min(idle_deadline)),
+            (expires_at, idle_deadline) => expires_at.or(idle_deadline)
+        }
+    }
+
+    /// Pinned entries never expire.
+    pub(crate) fn is_expired_at(&self, time_to_idle: Option<Duration>,
+            now: Instant) -> bool {
+        self.pins == 0 && self.deadline(time_to_idle)
+            .is_some_and(|deadline| deadline <= now)
+    }
+}
+
+/// Decides which entries of a cache are expired at a fixed point in time
+/// without borrowing the cache itself, so iterators holding only pointers to
+/// entries can skip expired ones.
+pub(crate) struct ExpiryCheck<'a, K, V> {
+    table: &'a RawTable<Entry<K, V>>,
+    meta: &'a [EntryMeta],
+    time_to_idle: Option<Duration>,
+    now: Instant
+}
+
+impl<'a, K, V> ExpiryCheck<'a, K, V> {
+    pub(crate) fn new(table: &'a RawTable<Entry<K, V>>, meta: &'a [EntryMeta],
+            time_to_idle: Option<Duration>, now: Instant)
+            -> ExpiryCheck<'a, K, V> {
+        ExpiryCheck {
+            table,
+            meta,
+            time_to_idle,
+            now
+        }
+    }
+
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    pub(crate) unsafe fn is_expired(&self, entry: EntryPtr<K, V>) -> bool {
+        self.meta[entry.index_in(self.table)]
+            .is_expired_at(self.time_to_idle, self.now)
+    }
+}
+
 pub(crate) struct Entry<K, V> {
     pub(crate) size: usize,
     pub(crate) prev: EntryPtr<K, V>,
@@ -92,6 +162,11 @@ impl<K, V> Entry<K, V> {
         self.value.assume_init_mut()
     }
 
+    /// Safety: Requires key and value to be initialized.
+    pub(crate) unsafe fn key_value_mut(&mut self) -> (&K, &mut V) {
+        (self.key.assume_init_ref(), self.value.assume_init_mut())
+    }
+
     /// Safety: Requires key and value to be initialized.
     pub(crate) unsafe fn into_key_value(self) -> (K, V) {
         (self.key.assume_init(), self.value.assume_init())
@@ -204,6 +279,16 @@ impl<K, V> EntryPtr<K, V> {
         self.ptr.is_null()
     }
 
+    /// Gets the index of the bucket of the given table in which the entry
+    /// pointed to by this pointer is stored.
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    pub(crate) unsafe fn index_in(&self, table: &RawTable<Entry<K, V>>)
+            -> usize {
+        table.data_end().as_ptr().offset_from(self.ptr) as usize - 1
+    }
+
     pub(crate) fn get(&self) -> &Entry<K, V> {
         unsafe { &*self.ptr }
     }
@@ -217,6 +302,12 @@ impl<K, V> EntryPtr<K, V> {
         &*self.ptr
     }
 
+    /// Safety: Must ensure the pointer is valid for the given lifetime and the
+    /// entry is not accessed through any other pointer during it.
+    pub(crate) unsafe fn get_extended_mut<'a>(self) -> &'a mut Entry<K, V> {
+        &mut *self.ptr
+    }
+
     /// Safety: Must ensure the entry is re-inserted at the appropriate
     /// location.
     pub(crate) unsafe fn unhinge(self) {
@@ -256,8 +347,7 @@ mod tests {
 
     #[test]
     fn entry_correctly_computes_size() {
-        let entry =
-            UnhingedEntry::new("hello".to_owned(), "world!".to_owned());
+        let size = entry_size(&"hello".to_owned(), &"world!".to_owned());
 
         let key_str_bytes = 5;
         let value_str_bytes = 6;
@@ -275,6 +365,6 @@ mod tests {
             + usize_bytes
             + 2 * ptr_bytes;
 
-        assert_eq!(expected_bytes, entry.size());
+        assert_eq!(expected_bytes, size);
     }
 }
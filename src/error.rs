@@ -22,6 +22,26 @@ pub enum InsertError<K, V> {
 
         /// The maximum size of the cache in bytes.
         max_size: usize
+    },
+
+    /// This error is raised if the entry to be inserted does not fit in the
+    /// memory of the cache that is not occupied by pinned entries (see
+    /// [LruCache::pin](crate::LruCache::pin)), so it could not be inserted
+    /// even if all unpinned entries were ejected.
+    WouldEjectPinned {
+
+        /// The key of the entry which did not fit.
+        key: K,
+
+        /// The value of the entry which did not fit.
+        value: V,
+
+        /// The computed size requirement of the entry if it were in the cache
+        /// in bytes.
+        entry_size: usize,
+
+        /// The memory of the cache not occupied by pinned entries in bytes.
+        available_memory: usize
     }
 }
 
@@ -29,7 +49,9 @@ impl<K, V> Display for InsertError<K, V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             InsertError::EntryTooLarge { .. } =>
-                write!(f, "entry does not fit in cache")
+                write!(f, "entry does not fit in cache"),
+            InsertError::WouldEjectPinned { .. } =>
+                write!(f, "entry does not fit beside pinned entries")
         }
     }
 }
@@ -60,6 +82,29 @@ pub enum MutateError<K, V> {
 
         /// The maximum size of the cache in bytes.
         max_size: usize
+    },
+
+    /// This error is raised if the memory requirement of an entry is raised
+    /// in a mutation above the memory of the cache that is not occupied by
+    /// other, pinned entries (see [LruCache::pin](crate::LruCache::pin)).
+    WouldEjectPinned {
+
+        /// The key of the mutated entry.
+        key: K,
+
+        /// The mutated value.
+        value: V,
+
+        /// The size requirement of the entry before its mutation in bytes.
+        old_entry_size: usize,
+
+        /// The size requirement of the entry after its mutation in bytes, if
+        /// it were in the cache.
+        new_entry_size: usize,
+
+        /// The memory of the cache not occupied by other pinned entries in
+        /// bytes.
+        available_memory: usize
     }
 }
 
@@ -67,7 +112,9 @@ impl<K, V> Display for MutateError<K, V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             MutateError::EntryTooLarge { .. } =>
-                write!(f, "modified entry does not fit in cache")
+                write!(f, "modified entry does not fit in cache"),
+            MutateError::WouldEjectPinned { .. } =>
+                write!(f, "modified entry does not fit beside pinned entries")
         }
     }
 }
@@ -108,6 +155,26 @@ pub enum TryInsertError<K, V> {
         free_memory: usize
     },
 
+    /// This error is raised if the entry to be inserted does not fit in the
+    /// memory of the cache that is not occupied by pinned entries (see
+    /// [LruCache::pin](crate::LruCache::pin)), so it could not be inserted
+    /// even if all unpinned entries were ejected.
+    WouldEjectPinned {
+
+        /// The key of the entry to insert.
+        key: K,
+
+        /// The value of the entry to insert.
+        value: V,
+
+        /// The computed size requirement of the entry if it were in the cache
+        /// in bytes.
+        entry_size: usize,
+
+        /// The memory of the cache not occupied by pinned entries in bytes.
+        available_memory: usize
+    },
+
     /// This error is raised if the amount of memory required to store an entry
     /// to be inserted is larger than the maximum of the cache.
     EntryTooLarge {
@@ -134,6 +201,8 @@ impl<K, V> Display for TryInsertError<K, V> {
                 write!(f, "key already has associated entry"),
             TryInsertError::WouldEjectLru { .. } =>
                 write!(f, "entry does not fit within remaining memory"),
+            TryInsertError::WouldEjectPinned { .. } =>
+                write!(f, "entry does not fit beside pinned entries"),
             TryInsertError::EntryTooLarge { .. } =>
                 write!(f, "entry does not fit in cache")
         }
@@ -162,6 +231,7 @@ impl<K, V> TryInsertError<K, V> {
         match self {
             TryInsertError::OccupiedEntry { key, value, .. } => (key, value),
             TryInsertError::WouldEjectLru { key, value, .. } => (key, value),
+            TryInsertError::WouldEjectPinned { key, value, .. } => (key, value),
             TryInsertError::EntryTooLarge { key, value, .. } => (key, value)
         }
     }
@@ -220,6 +290,7 @@ impl<K, V> TryInsertError<K, V> {
         match self {
             TryInsertError::OccupiedEntry { key, value, .. } => (key, value),
             TryInsertError::WouldEjectLru { key, value, .. } => (key, value),
+            TryInsertError::WouldEjectPinned { key, value, .. } => (key, value),
             TryInsertError::EntryTooLarge { key, value, .. } => (key, value)
         }
     }
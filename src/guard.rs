@@ -0,0 +1,209 @@
+use crate::{DefaultWeigher, LruCache, MutateError, Weigher};
+use crate::entry::EntryPtr;
+
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// A guard providing mutable access to a value of an [LruCache]. This is
+/// obtained by calling [LruCache::get_mut].
+///
+/// Once the guard is dropped, the size of the entry is updated, it is marked
+/// as most-recently-used, and, if necessary, least-recently-used entries are
+/// ejected to restore the memory requirement, just as with
+/// [LruCache::mutate]. If the value was expanded so much that the entry no
+/// longer fits inside the memory limit of the cache, it is removed and
+/// reported to the removal listener (see [LruCache::set_removal_listener])
+/// with [RemovalCause::EntryTooLarge](crate::RemovalCause::EntryTooLarge). To
+/// get the entry back instead, use [ValueGuard::commit] rather than dropping
+/// the guard.
+pub struct ValueGuard<'a, K, V, S, W = DefaultWeigher>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    cache: &'a mut LruCache<K, V, S, W>,
+    entry_ptr: EntryPtr<K, V>,
+    old_weight: usize
+}
+
+impl<'a, K, V, S, W> ValueGuard<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// given cache.
+    pub(crate) unsafe fn new(cache: &'a mut LruCache<K, V, S, W>,
+            entry_ptr: EntryPtr<K, V>) -> ValueGuard<'a, K, V, S, W> {
+        let old_weight = cache.weigh_ptr(entry_ptr);
+
+        ValueGuard {
+            cache,
+            entry_ptr,
+            old_weight
+        }
+    }
+
+    /// Gets a reference to the key of the entry whose value is guarded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert_eq!("apple", cache.get_mut("apple").unwrap().key());
+    /// ```
+    pub fn key(&self) -> &K {
+        unsafe { self.entry_ptr.get().key() }
+    }
+
+    /// Consumes the guard and applies the changes to the value to the cache,
+    /// as would happen if the guard were dropped. In contrast to dropping, any
+    /// error is reported to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError::EntryTooLarge] if the value was expanded so
+    /// much that the entry no longer fit inside the memory limit of the
+    /// cache, or an [MutateError::WouldEjectPinned] if it no longer fit beside
+    /// the other pinned entries (see [LruCache::pin]). If that is the case,
+    /// the entry is removed and its parts returned in the error data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, MutateError};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), vec![0u8; 8]).unwrap();
+    /// let mut guard = cache.get_mut("apple").unwrap();
+    /// guard.resize(1024, 0);
+    /// let result = guard.commit();
+    ///
+    /// assert!(matches!(result, Err(MutateError::EntryTooLarge { .. })));
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn commit(mut self) -> Result<(), MutateError<K, V>> {
+        let result = self.apply();
+        mem::forget(self);
+        result
+    }
+
+    fn apply(&mut self) -> Result<(), MutateError<K, V>> {
+        unsafe {
+            let new_weight = self.cache.weigh_ptr(self.entry_ptr);
+            self.cache.update_weight(self.entry_ptr, self.old_weight,
+                new_weight, LruCache::drop_ejected)
+        }
+    }
+}
+
+impl<'a, K, V, S, W> Deref for ValueGuard<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { self.entry_ptr.get().value() }
+    }
+}
+
+impl<'a, K, V, S, W> DerefMut for ValueGuard<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { self.entry_ptr.get_mut().value_mut() }
+    }
+}
+
+impl<'a, K, V, S, W> Drop for ValueGuard<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    fn drop(&mut self) {
+        if let Err(err) = self.apply() {
+            self.cache.drop_too_large(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::LruCache;
+
+    #[test]
+    fn dropping_guard_updates_size_and_sets_most_recently_used() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+
+        {
+            let mut guard = cache.get_mut(&0).unwrap();
+            guard.clear();
+            guard.shrink_to_fit();
+        }
+
+        assert_eq!(size_before - 8, cache.current_size());
+        assert_eq!(Some((&0, &vec![])), cache.peek_mru());
+    }
+
+    #[test]
+    fn dropping_guard_ejects_lru_if_overflowing() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.set_max_size(cache.current_size());
+        cache.get_mut(&1).unwrap().push(0);
+
+        assert_eq!(1, cache.len());
+        assert!(cache.contains(&1));
+        assert!(cache.current_size() <= cache.max_size());
+    }
+
+    #[test]
+    fn dropping_guard_removes_too_expanded_entry() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.get_mut(&0).unwrap().resize(1024, 0);
+
+        assert_eq!(1, cache.len());
+        assert!(!cache.contains(&0));
+    }
+
+    #[test]
+    fn committing_guard_reports_success() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+        let mut guard = cache.get_mut(&0).unwrap();
+        guard.extend_from_slice(&[1u8; 8]);
+        guard.shrink_to_fit();
+
+        assert_eq!(Ok(()), guard.commit());
+        assert_eq!(size_before + 8, cache.current_size());
+    }
+
+    #[test]
+    fn get_mut_on_non_existent_element_returns_none() {
+        let mut cache = LruCache::<u32, Vec<u8>>::new(1024);
+
+        assert!(cache.get_mut(&0).is_none());
+    }
+}
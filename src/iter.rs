@@ -1,45 +1,66 @@
-use crate::LruCache;
-use crate::entry::EntryPtr;
+use crate::{DefaultWeigher, LruCache, RemovalCause, Weigher};
+use crate::entry::{EntryPtr, ExpiryCheck};
 
+use std::hash::{BuildHasher, Hash};
 use std::iter::FusedIterator;
-use std::marker::PhantomData;
+use std::mem;
+use std::time::Instant;
 
 /// An iterator over references to the entries of an [LruCache] ordered from
 /// least- to most-recently-used. This is obtained by calling [LruCache::iter].
+///
+/// Entries which are expired (see [LruCache::insert_with_ttl]) at the time
+/// the iterator is created are skipped.
 pub struct Iter<'a, K, V> {
     next: EntryPtr<K, V>,
     next_back: EntryPtr<K, V>,
-    lifetime: PhantomData<&'a ()>
+    expiry_check: Option<ExpiryCheck<'a, K, V>>
 }
 
 impl<'a, K, V> Iter<'a, K, V> {
-    pub(crate) fn new<S>(cache: &LruCache<K, V, S>) -> Iter<K, V> {
+    pub(crate) fn new<S, W>(cache: &LruCache<K, V, S, W>) -> Iter<K, V> {
         if cache.is_empty() {
             Iter {
                 next: unsafe { EntryPtr::null() },
                 next_back: unsafe { EntryPtr::null() },
-                lifetime: PhantomData
+                expiry_check: None
             }
         }
         else {
             Iter {
                 next: cache.seal.get().prev,
                 next_back: cache.seal.get().next,
-                lifetime: PhantomData
+                expiry_check: cache.expiry_check()
             }
         }
     }
+
+    /// Safety: Requires both pointers to point to entries of the same cache,
+    /// with `next_back` being at least as recently used as `next`. The expiry
+    /// check must belong to that cache.
+    pub(crate) unsafe fn new_range(next: EntryPtr<K, V>,
+            next_back: EntryPtr<K, V>,
+            expiry_check: Option<ExpiryCheck<'a, K, V>>) -> Iter<'a, K, V> {
+        Iter {
+            next,
+            next_back,
+            expiry_check
+        }
+    }
+
+    fn is_expired(&self, entry: EntryPtr<K, V>) -> bool {
+        self.expiry_check.as_ref()
+            .is_some_and(|check| unsafe { check.is_expired(entry) })
+    }
 }
 
 impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<(&'a K, &'a V)> {
-        if self.next.is_null() {
-            None
-        }
-        else {
-            let entry = unsafe { self.next.get_extended() };
+        while !self.next.is_null() {
+            let entry_ptr = self.next;
+            let entry = unsafe { entry_ptr.get_extended() };
 
             if self.next == self.next_back {
                 self.next = unsafe { EntryPtr::null() };
@@ -48,18 +69,20 @@ impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V> {
                 self.next = entry.prev;
             }
 
-            unsafe { Some((entry.key(), entry.value())) }
+            if !self.is_expired(entry_ptr) {
+                return unsafe { Some((entry.key(), entry.value())) };
+            }
         }
+
+        None
     }
 }
 
 impl<'a, K: 'a, V: 'a> DoubleEndedIterator for Iter<'a, K, V> {
     fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
-        if self.next.is_null() {
-            None
-        }
-        else {
-            let entry = unsafe { self.next_back.get_extended() };
+        while !self.next.is_null() {
+            let entry_ptr = self.next_back;
+            let entry = unsafe { entry_ptr.get_extended() };
 
             if self.next_back == self.next {
                 self.next = unsafe { EntryPtr::null() };
@@ -68,8 +91,12 @@ impl<'a, K: 'a, V: 'a> DoubleEndedIterator for Iter<'a, K, V> {
                 self.next_back = entry.next;
             }
 
-            unsafe { Some((entry.key(), entry.value())) }
+            if !self.is_expired(entry_ptr) {
+                return unsafe { Some((entry.key(), entry.value())) };
+            }
         }
+
+        None
     }
 }
 
@@ -82,7 +109,7 @@ pub struct Keys<'a, K, V> {
 }
 
 impl<'a, K, V> Keys<'a, K, V> {
-    pub(crate) fn new<S>(cache: &'a LruCache<K, V, S>) -> Keys<'a, K, V> {
+    pub(crate) fn new<S, W>(cache: &'a LruCache<K, V, S, W>) -> Keys<'a, K, V> {
         Keys {
             iter: Iter::new(cache)
         }
@@ -113,7 +140,8 @@ pub struct Values<'a, K, V> {
 }
 
 impl<'a, K, V> Values<'a, K, V> {
-    pub(crate) fn new<S>(cache: &'a LruCache<K, V, S>) -> Values<'a, K, V> {
+    pub(crate) fn new<S, W>(cache: &'a LruCache<K, V, S, W>)
+            -> Values<'a, K, V> {
         Values {
             iter: Iter::new(cache)
         }
@@ -136,13 +164,247 @@ impl<'a, K: 'a, V: 'a> DoubleEndedIterator for Values<'a, K, V> {
 
 impl<'a, K: 'a, V: 'a> FusedIterator for Values<'a, K, V> { }
 
+/// An iterator over mutable references to the values of the entries of an
+/// [LruCache] ordered from least- to most-recently-used. This is obtained by
+/// calling [LruCache::iter_mut].
+///
+/// Entries which are expired (see [LruCache::insert_with_ttl]) at the time
+/// the iterator is created are skipped. Sizes are not updated while
+/// iterating. Instead, once the iterator is dropped, the size of every entry
+/// handed out is adjusted by the change of its weight. Changes made through
+/// the references after that are not accounted for. No entries are removed
+/// when the iterator is dropped, since the references may still be in use.
+/// If entries were expanded beyond the memory requirement, least-recently-used
+/// entries are ejected by the next insertion.
+pub struct IterMut<'a, K, V, S, W = DefaultWeigher>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    cache: &'a mut LruCache<K, V, S, W>,
+    next: EntryPtr<K, V>,
+    next_back: EntryPtr<K, V>,
+
+    // The time at which entries are checked for expiry, if any entry may be
+    // expired at all.
+    now: Option<Instant>,
+
+    // Each entry handed out with its weight at that time, so only the change
+    // of the weight is applied to its size once this iterator is dropped.
+    handed_out: Vec<(EntryPtr<K, V>, usize)>
+}
+
+impl<'a, K, V, S, W> IterMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>)
+            -> IterMut<'a, K, V, S, W> {
+        let (next, next_back) = if cache.is_empty() {
+            unsafe { (EntryPtr::null(), EntryPtr::null()) }
+        }
+        else {
+            (cache.seal.get().prev, cache.seal.get().next)
+        };
+        let now = if cache.may_expire() {
+            Some(cache.now())
+        }
+        else {
+            None
+        };
+
+        IterMut {
+            cache,
+            next,
+            next_back,
+            now,
+            handed_out: Vec::new()
+        }
+    }
+
+    fn is_expired(&self, entry_ptr: EntryPtr<K, V>) -> bool {
+        self.now.is_some_and(|now| unsafe {
+            self.cache.meta(entry_ptr)
+                .is_expired_at(self.cache.time_to_idle, now)
+        })
+    }
+
+    fn hand_out(&mut self, entry_ptr: EntryPtr<K, V>) -> EntryPtr<K, V> {
+        let weight = unsafe { self.cache.weigh_ptr(entry_ptr) };
+        self.handed_out.push((entry_ptr, weight));
+        entry_ptr
+    }
+
+    fn next_ptr(&mut self) -> Option<EntryPtr<K, V>> {
+        while !self.next.is_null() {
+            let entry_ptr = self.next;
+
+            if self.next == self.next_back {
+                self.next = unsafe { EntryPtr::null() };
+            }
+            else {
+                self.next = entry_ptr.get().prev;
+            }
+
+            if !self.is_expired(entry_ptr) {
+                return Some(self.hand_out(entry_ptr));
+            }
+        }
+
+        None
+    }
+
+    fn next_back_ptr(&mut self) -> Option<EntryPtr<K, V>> {
+        while !self.next.is_null() {
+            let entry_ptr = self.next_back;
+
+            if self.next_back == self.next {
+                self.next = unsafe { EntryPtr::null() };
+            }
+            else {
+                self.next_back = entry_ptr.get().next;
+            }
+
+            if !self.is_expired(entry_ptr) {
+                return Some(self.hand_out(entry_ptr));
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, K, V, S, W> Iterator for IterMut<'a, K, V, S, W>
+where
+    K: Eq + Hash + 'a,
+    V: 'a,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        self.next_ptr().map(|entry_ptr| unsafe {
+            entry_ptr.get_extended_mut().key_value_mut()
+        })
+    }
+}
+
+impl<'a, K, V, S, W> DoubleEndedIterator for IterMut<'a, K, V, S, W>
+where
+    K: Eq + Hash + 'a,
+    V: 'a,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        self.next_back_ptr().map(|entry_ptr| unsafe {
+            entry_ptr.get_extended_mut().key_value_mut()
+        })
+    }
+}
+
+impl<'a, K, V, S, W> FusedIterator for IterMut<'a, K, V, S, W>
+where
+    K: Eq + Hash + 'a,
+    V: 'a,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{ }
+
+impl<'a, K, V, S, W> Drop for IterMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    fn drop(&mut self) {
+        // Applying only the change of the weight retains sizes which were
+        // assigned explicitly, e.g. by LruCache::insert_with_size. Entries
+        // handed out may still be referenced, so none may be removed here.
+
+        for (entry_ptr, old_weight) in mem::take(&mut self.handed_out) {
+            unsafe {
+                let new_weight = self.cache.weigh_ptr(entry_ptr);
+                self.cache.reweigh_entry(entry_ptr, old_weight, new_weight);
+            }
+        }
+    }
+}
+
+/// An iterator over mutable references to the values of an [LruCache] ordered
+/// from least- to most-recently-used. This is obtained by calling
+/// [LruCache::values_mut].
+///
+/// As with [IterMut], expired entries are skipped and sizes are updated once
+/// the iterator is dropped.
+pub struct ValuesMut<'a, K, V, S, W = DefaultWeigher>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    iter_mut: IterMut<'a, K, V, S, W>
+}
+
+impl<'a, K, V, S, W> ValuesMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>)
+            -> ValuesMut<'a, K, V, S, W> {
+        ValuesMut {
+            iter_mut: IterMut::new(cache)
+        }
+    }
+}
+
+impl<'a, K, V, S, W> Iterator for ValuesMut<'a, K, V, S, W>
+where
+    K: Eq + Hash + 'a,
+    V: 'a,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<&'a mut V> {
+        self.iter_mut.next().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V, S, W> DoubleEndedIterator for ValuesMut<'a, K, V, S, W>
+where
+    K: Eq + Hash + 'a,
+    V: 'a,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    fn next_back(&mut self) -> Option<&'a mut V> {
+        self.iter_mut.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V, S, W> FusedIterator for ValuesMut<'a, K, V, S, W>
+where
+    K: Eq + Hash + 'a,
+    V: 'a,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{ }
+
 struct TakingIterator<K, V> {
     next: EntryPtr<K, V>,
     next_back: EntryPtr<K, V>,
 }
 
 impl<K, V> TakingIterator<K, V> {
-    fn new<S>(cache: &LruCache<K, V, S>) -> TakingIterator<K, V> {
+    fn new<S, W>(cache: &LruCache<K, V, S, W>) -> TakingIterator<K, V> {
         if cache.is_empty() {
             TakingIterator {
                 next: unsafe { EntryPtr::null() },
@@ -207,13 +469,19 @@ impl<K, V> DoubleEndedIterator for TakingIterator<K, V> {
 /// An iterator that drains key-value-pairs from an [LruCache] ordered from
 /// least- to most-recently-used. This is obtained by calling
 /// [LruCache::drain].
-pub struct Drain<'a, K, V, S> {
+///
+/// Yielded entries are not reported to the removal listener (see
+/// [LruCache::set_removal_listener]). If the iterator is dropped before being
+/// exhausted, the remaining entries are dropped and reported with
+/// [RemovalCause::Cleared].
+pub struct Drain<'a, K, V, S, W = DefaultWeigher> {
     iterator: TakingIterator<K, V>,
-    cache: &'a mut LruCache<K, V, S>
+    cache: &'a mut LruCache<K, V, S, W>
 }
 
-impl<'a, K, V, S> Drain<'a, K, V, S> {
-    pub(crate) fn new(cache: &'a mut LruCache<K, V, S>) -> Drain<'a, K, V, S> {
+impl<'a, K, V, S, W> Drain<'a, K, V, S, W> {
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>)
+            -> Drain<'a, K, V, S, W> {
         Drain {
             iterator: TakingIterator::new(cache),
             cache
@@ -221,7 +489,7 @@ impl<'a, K, V, S> Drain<'a, K, V, S> {
     }
 }
 
-impl<'a, K, V, S> Iterator for Drain<'a, K, V, S> {
+impl<'a, K, V, S, W> Iterator for Drain<'a, K, V, S, W> {
     type Item = (K, V);
 
     fn next(&mut self) -> Option<(K, V)> {
@@ -229,17 +497,19 @@ impl<'a, K, V, S> Iterator for Drain<'a, K, V, S> {
     }
 }
 
-impl<'a, K, V, S> DoubleEndedIterator for Drain<'a, K, V, S> {
+impl<'a, K, V, S, W> DoubleEndedIterator for Drain<'a, K, V, S, W> {
     fn next_back(&mut self) -> Option<(K, V)> {
         self.iterator.next_back()
     }
 }
 
-impl<'a, K, V, S> Drop for Drain<'a, K, V, S> {
+impl<'a, K, V, S, W> Drop for Drain<'a, K, V, S, W> {
     fn drop(&mut self) {
         // Drop all allocated memory of the remaining elements.
 
-        for _ in self.by_ref() { }
+        for (key, value) in self.iterator.by_ref() {
+            self.cache.notify_removal(key, value, RemovalCause::Cleared);
+        }
 
         // Set the cache as empty.
 
@@ -247,22 +517,182 @@ impl<'a, K, V, S> Drop for Drain<'a, K, V, S> {
         self.cache.seal.get_mut().prev = self.cache.seal;
 
         self.cache.current_size = 0;
+        self.cache.pinned_size = 0;
         self.cache.table.clear_no_drop();
     }
 }
 
-impl<'a, K, V, S> FusedIterator for Drain<'a, K, V, S> { }
+impl<'a, K, V, S, W> FusedIterator for Drain<'a, K, V, S, W> { }
+
+/// An iterator that removes all entries of an [LruCache] which satisfy a
+/// predicate and iterates over them as key-value-pairs ordered from least- to
+/// most-recently-used. This is obtained by calling [LruCache::extract_if].
+///
+/// Entries are only removed as the iterator advances. If it is dropped before
+/// being exhausted, the remaining entries stay in the cache. Since removed
+/// entries are handed to the caller, they are not reported to the removal
+/// listener (see [LruCache::set_removal_listener]).
+pub struct ExtractIf<'a, K, V, S, F, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>,
+    next: EntryPtr<K, V>,
+    pred: F
+}
+
+impl<'a, K, V, S, F, W> ExtractIf<'a, K, V, S, F, W> {
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>, pred: F)
+            -> ExtractIf<'a, K, V, S, F, W> {
+        let next = cache.seal.get().prev;
+
+        ExtractIf {
+            cache,
+            next,
+            pred
+        }
+    }
+}
+
+impl<'a, K, V, S, F, W> Iterator for ExtractIf<'a, K, V, S, F, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    F: FnMut(&K, &V) -> bool
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        while self.next != self.cache.seal {
+            let entry_ptr = self.next;
+            let entry = entry_ptr.get();
+            self.next = entry.prev;
+
+            unsafe {
+                if (self.pred)(entry.key(), entry.value()) {
+                    return Some(self.cache.remove_ptr(entry_ptr));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.cache.len()))
+    }
+}
+
+impl<'a, K, V, S, F, W> FusedIterator for ExtractIf<'a, K, V, S, F, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    F: FnMut(&K, &V) -> bool
+{ }
+
+/// An iterator that ejects least-recently-used entries from an [LruCache]
+/// until its current size is at most a target, and iterates over them as
+/// key-value-pairs ordered from least- to most-recently-used. Pinned entries
+/// are skipped. This is obtained by calling [LruCache::resize],
+/// [LruCache::evict_to], or [LruCache::evict_bytes].
+///
+/// Entries are ejected as the iterator advances. If it is dropped before being
+/// exhausted, the remaining entries are ejected and dropped, so the target is
+/// reached in any case (unless only pinned entries remain). Only those dropped
+/// entries are reported to the removal listener (see
+/// [LruCache::set_removal_listener]), since yielded ones are handed to the
+/// caller.
+pub struct Eject<'a, K, V, S, W = DefaultWeigher>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+    cache: &'a mut LruCache<K, V, S, W>,
+    tail: EntryPtr<K, V>,
+    target: usize,
+    cause: RemovalCause
+}
+
+impl<'a, K, V, S, W> Eject<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>, target: usize,
+            cause: RemovalCause) -> Eject<'a, K, V, S, W> {
+        let tail = cache.seal.get().prev;
+
+        Eject {
+            cache,
+            tail,
+            target,
+            cause
+        }
+    }
+
+    fn eject_next(&mut self) -> Option<(K, V)> {
+        while self.cache.current_size > self.target &&
+                self.tail != self.cache.seal {
+            let entry_ptr = self.tail;
+            self.tail = entry_ptr.get().prev;
+
+            if unsafe { self.cache.meta(entry_ptr).pins } == 0 {
+                return Some(unsafe { self.cache.remove_ptr(entry_ptr) });
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, K, V, S, W> Iterator for Eject<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.eject_next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.cache.current_size > self.target {
+            // Only pinned entries may remain, so none may be ejected.
+
+            (0, Some(self.cache.len()))
+        }
+        else {
+            (0, Some(0))
+        }
+    }
+}
+
+impl<'a, K, V, S, W> Drop for Eject<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+    fn drop(&mut self) {
+        while let Some((key, value)) = self.eject_next() {
+            self.cache.notify_removal(key, value, self.cause);
+        }
+    }
+}
+
+impl<'a, K, V, S, W> FusedIterator for Eject<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{ }
 
 /// An iterator that takes ownership of an [LruCache] and iterates over its
 /// entries as key-value-pairs ordered from least- to most-recently-used. This
 /// is obtained by calling [IntoIterator::into_iter] on the cache.
-pub struct IntoIter<K, V, S> {
+pub struct IntoIter<K, V, S, W = DefaultWeigher> {
     iterator: TakingIterator<K, V>,
-    cache: LruCache<K, V, S>
+    cache: LruCache<K, V, S, W>
 }
 
-impl<K, V, S> IntoIter<K, V, S> {
-    pub(crate) fn new(cache: LruCache<K, V, S>) -> IntoIter<K, V, S> {
+impl<K, V, S, W> IntoIter<K, V, S, W> {
+    pub(crate) fn new(cache: LruCache<K, V, S, W>) -> IntoIter<K, V, S, W> {
         IntoIter {
             iterator: TakingIterator::new(&cache),
             cache
@@ -270,7 +700,7 @@ impl<K, V, S> IntoIter<K, V, S> {
     }
 }
 
-impl<K, V, S> Iterator for IntoIter<K, V, S> {
+impl<K, V, S, W> Iterator for IntoIter<K, V, S, W> {
     type Item = (K, V);
 
     fn next(&mut self) -> Option<(K, V)> {
@@ -278,13 +708,13 @@ impl<K, V, S> Iterator for IntoIter<K, V, S> {
     }
 }
 
-impl<K, V, S> DoubleEndedIterator for IntoIter<K, V, S> {
+impl<K, V, S, W> DoubleEndedIterator for IntoIter<K, V, S, W> {
     fn next_back(&mut self) -> Option<(K, V)> {
         self.iterator.next_back()
     }
 }
 
-impl<K, V, S> Drop for IntoIter<K, V, S> {
+impl<K, V, S, W> Drop for IntoIter<K, V, S, W> {
     fn drop(&mut self) {
         // Drop all allocated memory of the remaining elements.
         for _ in self.by_ref() { }
@@ -297,19 +727,19 @@ impl<K, V, S> Drop for IntoIter<K, V, S> {
 /// An iterator that takes ownership of an [LruCache] and iterates over its
 /// keys ordered from least- to most-recently-used. This is obtained by calling
 /// [LruCache::into_keys].
-pub struct IntoKeys<K, V, S> {
-    into_iter: IntoIter<K, V, S>
+pub struct IntoKeys<K, V, S, W = DefaultWeigher> {
+    into_iter: IntoIter<K, V, S, W>
 }
 
-impl<K, V, S> IntoKeys<K, V, S> {
-    pub(crate) fn new(cache: LruCache<K, V, S>) -> IntoKeys<K, V, S> {
+impl<K, V, S, W> IntoKeys<K, V, S, W> {
+    pub(crate) fn new(cache: LruCache<K, V, S, W>) -> IntoKeys<K, V, S, W> {
         IntoKeys {
             into_iter: IntoIter::new(cache)
         }
     }
 }
 
-impl<K, V, S> Iterator for IntoKeys<K, V, S>  {
+impl<K, V, S, W> Iterator for IntoKeys<K, V, S, W>  {
     type Item = K;
 
     fn next(&mut self) -> Option<K> {
@@ -317,7 +747,7 @@ impl<K, V, S> Iterator for IntoKeys<K, V, S>  {
     }
 }
 
-impl<K, V, S> DoubleEndedIterator for IntoKeys<K, V, S> {
+impl<K, V, S, W> DoubleEndedIterator for IntoKeys<K, V, S, W> {
     fn next_back(&mut self) -> Option<K> {
         self.into_iter.next_back().map(|(k, _)| k)
     }
@@ -326,19 +756,19 @@ impl<K, V, S> DoubleEndedIterator for IntoKeys<K, V, S> {
 /// An iterator that takes ownership of an [LruCache] and iterates over its
 /// values ordered from least- to most-recently-used. This is obtained by
 /// calling [LruCache::into_values].
-pub struct IntoValues<K, V, S> {
-    into_iter: IntoIter<K, V, S>
+pub struct IntoValues<K, V, S, W = DefaultWeigher> {
+    into_iter: IntoIter<K, V, S, W>
 }
 
-impl<K, V, S> IntoValues<K, V, S> {
-    pub(crate) fn new(cache: LruCache<K, V, S>) -> IntoValues<K, V, S> {
+impl<K, V, S, W> IntoValues<K, V, S, W> {
+    pub(crate) fn new(cache: LruCache<K, V, S, W>) -> IntoValues<K, V, S, W> {
         IntoValues {
             into_iter: IntoIter::new(cache)
         }
     }
 }
 
-impl<K, V, S> Iterator for IntoValues<K, V, S>  {
+impl<K, V, S, W> Iterator for IntoValues<K, V, S, W>  {
     type Item = V;
 
     fn next(&mut self) -> Option<V> {
@@ -346,7 +776,7 @@ impl<K, V, S> Iterator for IntoValues<K, V, S>  {
     }
 }
 
-impl<K, V, S> DoubleEndedIterator for IntoValues<K, V, S> {
+impl<K, V, S, W> DoubleEndedIterator for IntoValues<K, V, S, W> {
     fn next_back(&mut self) -> Option<V> {
         self.into_iter.next_back().map(|(_, v)| v)
     }
@@ -359,6 +789,193 @@ mod tests {
 
     use crate::{HeapSize, LruCache};
     use crate::tests::{large_test_cache, singleton_test_cache};
+    use crate::tests::large_test_cache_with_expired_entry;
+    use crate::tests::register_removal_log;
+
+    #[test]
+    fn extract_if_yields_matching_entries_in_lru_order() {
+        let mut cache = large_test_cache();
+        let extracted = cache.extract_if(|k, _| k.starts_with('h'))
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![("hello", "world"), ("hi", "venus")], extracted);
+        assert_eq!(vec!["greetings", "ahoy", "good morning"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(3, cache.len());
+    }
+
+    #[test]
+    fn extract_if_does_not_report_extracted_entries() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        let extracted = cache.extract_if(|k, _| k.starts_with('h')).count();
+
+        assert_eq!(2, extracted);
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dropping_extract_if_early_keeps_remaining_entries() {
+        let mut cache = large_test_cache();
+        let size_before = cache.current_size();
+
+        {
+            let mut extract_if = cache.extract_if(|_, _| true);
+
+            assert_eq!(Some(("hello", "world")), extract_if.next());
+        }
+
+        assert_eq!(vec!["greetings", "ahoy", "hi", "good morning"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert!(cache.current_size() < size_before);
+    }
+
+    #[test]
+    fn dropping_eject_early_ejects_remaining_entries() {
+        let mut cache = large_test_cache();
+        let max_size = cache.current_size() / 2;
+
+        {
+            let mut eject = cache.resize(max_size);
+
+            assert_eq!(Some(("hello", "world")), eject.next());
+        }
+
+        assert!(cache.current_size() <= max_size);
+        assert_eq!(Some((&"good morning", &"jupiter")), cache.peek_mru());
+    }
+
+    #[test]
+    fn iter_mut_updates_sizes_on_drop() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+
+        {
+            let mut iter_mut = cache.iter_mut();
+
+            for (_, value) in &mut iter_mut {
+                value.truncate(4);
+                value.shrink_to_fit();
+            }
+        }
+
+        assert_eq!(size_before - 8, cache.current_size());
+        assert_eq!(vec![0, 1], cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter_mut_remeasures_only_entries_handed_out() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+
+        {
+            let mut iter_mut = cache.iter_mut();
+            let (_, first) = (&mut iter_mut).next().unwrap();
+            first.push(0);
+            first.shrink_to_fit();
+            let (_, last) = (&mut iter_mut).next_back().unwrap();
+            last.push(0);
+            last.shrink_to_fit();
+        }
+
+        assert_eq!(size_before + 2, cache.current_size());
+    }
+
+    #[test]
+    fn iter_mut_keeps_explicitly_assigned_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert_with_size(0, vec![0u8; 8], 100).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        let other_size = cache.current_size() - 100;
+
+        for _ in &mut cache.iter_mut() { }
+
+        assert_eq!(100 + other_size, cache.current_size());
+
+        {
+            let mut values_mut = cache.values_mut();
+            let value = (&mut values_mut).next().unwrap();
+            value.push(0);
+            value.shrink_to_fit();
+        }
+
+        assert_eq!(101 + other_size, cache.current_size());
+    }
+
+    #[test]
+    fn iter_mut_defers_ejection_to_next_insertion() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        let max_size = cache.current_size();
+        cache.set_max_size(max_size);
+
+        if let Some(value) = cache.values_mut().next_back() {
+            value.reserve_exact(8);
+        }
+
+        assert_eq!(3, cache.len());
+        assert_eq!(max_size + 8, cache.current_size());
+
+        cache.insert(3, Vec::new()).unwrap();
+
+        assert_eq!(vec![1, 2, 3], cache.keys().copied().collect::<Vec<_>>());
+        assert!(cache.current_size() <= cache.max_size());
+    }
+
+    #[test]
+    fn iter_mut_keeps_references_valid_after_drop() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+
+        let values = cache.values_mut().collect::<Vec<_>>();
+
+        for value in values {
+            value.push(1);
+        }
+
+        assert_eq!(Some(&vec![0, 0, 0, 0, 0, 0, 0, 0, 1]), cache.peek(&0));
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn iter_mut_is_an_iterator_itself() {
+        let mut cache = large_test_cache();
+        let mut keys = Vec::new();
+
+        for (key, value) in cache.iter_mut() {
+            keys.push(*key);
+            *value = "earth";
+        }
+
+        assert_eq!(vec!["hello", "greetings", "ahoy", "hi", "good morning"],
+            keys);
+
+        cache.iter_mut().rev().for_each(|(_, value)| *value = "mars");
+
+        assert_eq!(vec!["mars"; 5],
+            cache.values().copied().collect::<Vec<_>>());
+        assert_eq!(vec![5, 9, 4, 2, 12],
+            cache.iter_mut().map(|(key, _)| key.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter_mut_and_values_mut_skip_expired_entries() {
+        let mut cache = large_test_cache_with_expired_entry();
+
+        assert_eq!(vec!["hello", "greetings", "ahoy", "hi", "good morning"],
+            cache.iter_mut().map(|(key, _)| *key).collect::<Vec<_>>());
+        assert_eq!(vec!["jupiter", "venus", "mars", "moon", "world"],
+            cache.values_mut().rev().map(|value| *value)
+                .collect::<Vec<_>>());
+    }
 
     #[test]
     fn iter_works_for_larger_cache() {
@@ -75,25 +75,47 @@
 //!
 //! For further details on how to use the cache, see the [LruCache] struct.
 
-use std::borrow::Borrow;
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{BuildHasher, Hash};
 use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use hashbrown::hash_map::DefaultHashBuilder;
-use hashbrown::raw::RawTable;
+use hashbrown::raw::{Bucket, RawTable};
 use hashbrown::TryReserveError;
 
-use entry::{Entry, EntryPtr, UnhingedEntry};
+use entry::{Entry, EntryMeta, EntryPtr, ExpiryCheck, NO_META, UnhingedEntry};
+pub use cache_entry::{CacheEntry, OccupiedEntry, VacantEntry};
+pub use clock::{Clock, SystemClock};
+pub use cursor::CursorMut;
 pub use entry::entry_size;
 pub use error::{InsertError, MutateError, TryInsertError};
-pub use iter::{Drain, IntoIter, IntoKeys, IntoValues, Iter, Keys, Values};
+pub use guard::ValueGuard;
+pub use hashbrown::Equivalent;
+pub use iter::{Drain, Eject, ExtractIf, IntoIter, IntoKeys, IntoValues, Iter,
+    IterMut, Keys, Values, ValuesMut};
+pub use listener::RemovalCause;
 pub use mem_size::{HeapSize, MemSize, ValueSize};
+pub use merge::ConflictPolicy;
+pub use raw_entry::{RawEntryBuilder, RawEntryBuilderMut, RawEntryMut,
+    RawOccupiedEntryMut, RawVacantEntryMut};
+pub use weigher::{DefaultWeigher, Weigher};
 
+use listener::RemovalListener;
+
+mod cache_entry;
+mod clock;
+mod cursor;
 mod entry;
 mod error;
+mod guard;
 mod iter;
+mod listener;
 mod mem_size;
+mod merge;
+mod raw_entry;
+mod weigher;
 
 /// An LRU (least-recently-used) cache that stores values associated with keys.
 /// Insertion, retrieval, and removal all have average-case complexity in O(1).
@@ -110,18 +132,43 @@ mod mem_size;
 /// Note that both the key type `K` and the value type `V` must implement the
 /// [MemSize] trait to allow for size estimation in normal usage. In addition,
 /// the key type `K` is required to implement [Hash] and [Eq] for most
-/// meaningful operations.
+/// meaningful operations. Alternatively, a custom [Weigher] of type `W` can be
+/// provided with [LruCache::with_weigher] to determine the size of entries,
+/// in which case neither type needs to implement [MemSize].
 ///
 /// Furthermore, the hasher type `S` must implement the [BuildHasher] trait for
 /// non-trivial functionality.
 ///
+/// Lookups accept any key type `Q` that implements [Equivalent] for `K` and
+/// hashes like the equivalent keys. This includes any `Q` that `K` implements
+/// [Borrow](std::borrow::Borrow) for. Otherwise, [Equivalent] can be
+/// implemented manually, e.g. to look up a `(String, u32)` by a
+/// `(&str, u32)` without allocating.
+///
 /// Mutable access is not allowed directly, since it may change the size of an
 /// entry. It must be done either by removing the element using
-/// [LruCache::remove] and inserting it again, or passing a mutating closure to
-/// [LruCache::mutate].
-pub struct LruCache<K, V, S = DefaultHashBuilder> {
+/// [LruCache::remove] and inserting it again, passing a mutating closure to
+/// [LruCache::mutate], or using the size-tracking guard returned by
+/// [LruCache::get_mut].
+pub struct LruCache<K, V, S = DefaultHashBuilder, W = DefaultWeigher> {
     table: RawTable<Entry<K, V>>,
 
+    // Pins, expiration and access times, and access stamps of the entries,
+    // indexed by the bucket in which the entry is stored. This is empty until
+    // the first entry is inserted. Afterwards, it has one slot per bucket of
+    // the table, which is overwritten whenever an entry is inserted into that
+    // bucket.
+    meta: Vec<EntryMeta>,
+
+    // The logical access stamp most recently given to an entry. Each cache
+    // counts its own accesses, see LruCache::merge.
+    access_counter: u64,
+
+    // Whether any entry was ever given an expiration time. Together with the
+    // time-to-idle, this decides whether entries may have to be checked for
+    // expiry at all. It is never reset, as that would require a scan.
+    expiration_used: bool,
+
     // The seal is a dummy entry that is simultaneously in front of the head
     // and behind the tail of the list. You can imagine it as connecting the
     // list to a cycle.
@@ -136,8 +183,17 @@ pub struct LruCache<K, V, S = DefaultHashBuilder> {
 
     seal: EntryPtr<K, V>,
     current_size: usize,
+    pinned_size: usize,
     max_size: usize,
-    hash_builder: S
+    hash_builder: S,
+    removal_listener: Option<RemovalListener<K, V>>,
+    default_ttl: Option<Duration>,
+    time_to_idle: Option<Duration>,
+
+    // If no clock is set, the system clock is used. This avoids allocating
+    // for the common case of a cache without custom clock.
+    clock: Option<Arc<dyn Clock>>,
+    weigher: W
 }
 
 impl<K, V> LruCache<K, V> {
@@ -160,8 +216,8 @@ impl<K, V> LruCache<K, V> {
     /// let cache: LruCache<String, String> = LruCache::new(16 * 1024);
     /// ```
     pub fn new(max_size: usize) -> LruCache<K, V> {
-        LruCache::with_table_and_hasher(max_size, RawTable::new(),
-            DefaultHashBuilder::default())
+        LruCache::with_table_hasher_and_weigher(max_size, RawTable::new(),
+            DefaultHashBuilder::default(), DefaultWeigher)
     }
 
     /// Creates a new, empty LRU cache with the given maximum memory size and
@@ -186,25 +242,74 @@ impl<K, V> LruCache<K, V> {
     /// let cache: LruCache<String, String> = LruCache::with_capacity(4096, 8);
     /// ```
     pub fn with_capacity(max_size: usize, capacity: usize) -> LruCache<K, V> {
-        LruCache::with_table_and_hasher(max_size,
-            RawTable::with_capacity(capacity), DefaultHashBuilder::default())
+        LruCache::with_table_hasher_and_weigher(max_size,
+            RawTable::with_capacity(capacity), DefaultHashBuilder::default(),
+            DefaultWeigher)
     }
 }
 
-impl<K, V, S> LruCache<K, V, S> {
+impl<K, V> LruCache<K, V>
+where
+    K: Eq + Hash + MemSize,
+    V: MemSize
+{
 
-    fn with_table_and_hasher(max_size: usize, table: RawTable<Entry<K, V>>,
-            hash_builder: S) -> LruCache<K, V, S> {
-        let seal = EntryPtr::new_seal();
+    /// Creates a new LRU cache with the given maximum memory size and fills
+    /// it with the entries of the given iterator. These are inserted in the
+    /// given order as with [LruCache::insert], so the last entry is the
+    /// most-recently-used one and earlier entries may be ejected to make room
+    /// for later ones. Capacity is reserved up front, so the cache does not
+    /// have to reallocate repeatedly while being filled.
+    ///
+    /// Entries which are too large to fit inside the cache on their own are
+    /// not inserted. Instead, they are returned alongside the cache as
+    /// [InsertError::EntryTooLarge], in the order they were encountered.
+    ///
+    /// # Arguments
+    ///
+    /// * `iter`: An iterator over the key-value-pairs to insert.
+    /// * `max_size`: The maximum number of bytes that the sum of the memory
+    /// estimates of all entries may occupy. It is important to note that this
+    /// bound may be exceeded in total memory requirement of the created data
+    /// structure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{InsertError, LruCache};
+    ///
+    /// let entries = vec![
+    ///     ("apple".to_owned(), "sweet".to_owned()),
+    ///     ("lemon".to_owned(), "sour".repeat(1024)),
+    ///     ("peach".to_owned(), "sweet".to_owned())
+    /// ];
+    /// let (mut cache, rejected) =
+    ///     LruCache::from_iter_with_max_size(entries, 1024);
+    ///
+    /// assert_eq!(2, cache.len());
+    /// assert_eq!(Some((&"peach".to_owned(), &"sweet".to_owned())),
+    ///     cache.peek_mru());
+    /// assert!(matches!(rejected.as_slice(),
+    ///     [InsertError::EntryTooLarge { .. }]));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn from_iter_with_max_size<I>(iter: I, max_size: usize)
+        -> (LruCache<K, V>, Vec<InsertError<K, V>>)
+    where
+        I: IntoIterator<Item = (K, V)>
+    {
+        // Every entry weighs at least the size of its slot in the table.
 
-        LruCache {
-            table,
-            seal,
-            current_size: 0,
-            max_size,
-            hash_builder
-        }
+        let mut cache = LruCache::new(max_size);
+        let max_entries = max_size / mem::size_of::<Entry<K, V>>();
+        let mut rejected = Vec::new();
+        cache.extend_with(iter, max_entries, |err| rejected.push(err));
+
+        (cache, rejected)
     }
+}
+
+impl<K, V, S> LruCache<K, V, S> {
 
     /// Creates a new, empty LRU cache with the given maximum memory size which
     /// will use the given hash builder to hash keys.
@@ -229,8 +334,8 @@ impl<K, V, S> LruCache<K, V, S> {
     /// let cache: LruCache<String, String> = LruCache::with_hasher(4096, s);
     /// ```
     pub fn with_hasher(max_size: usize, hash_builder: S) -> LruCache<K, V, S> {
-        LruCache::with_table_and_hasher(max_size, RawTable::new(),
-            hash_builder)
+        LruCache::with_table_hasher_and_weigher(max_size, RawTable::new(),
+            hash_builder, DefaultWeigher)
     }
 
     /// Creates a new, empty LRU cache with the given maximum memory size and
@@ -262,8 +367,330 @@ impl<K, V, S> LruCache<K, V, S> {
     /// ```
     pub fn with_capacity_and_hasher(max_size: usize, capacity: usize,
             hash_builder: S) -> LruCache<K, V, S> {
-        LruCache::with_table_and_hasher(max_size,
-            RawTable::with_capacity(capacity), hash_builder)
+        LruCache::with_table_hasher_and_weigher(max_size,
+            RawTable::with_capacity(capacity), hash_builder, DefaultWeigher)
+    }
+}
+
+impl<K, V, W> LruCache<K, V, DefaultHashBuilder, W> {
+
+    /// Creates a new, empty LRU cache with the given maximum weight which will
+    /// use the given [Weigher] to determine the weight of each entry instead
+    /// of its estimated memory requirement. The sum of the weights of all
+    /// entries never exceeds the maximum weight. This allows for caching
+    /// entries whose cost is not their memory, or whose key or value type
+    /// does not implement [MemSize].
+    ///
+    /// Throughout the cache, sizes are then measured in units of weight. For
+    /// example, [LruCache::current_size] returns the total weight of all
+    /// entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_weight`: The maximum weight that the sum of the weights of all
+    ///   entries may reach.
+    /// * `weigher`: The weigher used to determine the weight of each entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// #[derive(Debug)]
+    /// struct Handle {
+    ///     len: usize
+    /// }
+    ///
+    /// // Create an LRU cache which holds handles of at most 4 KiB total
+    /// // length.
+    /// let mut cache =
+    ///     LruCache::with_weigher(4096, |_: &u32, h: &Handle| h.len);
+    /// cache.insert(0, Handle { len: 3072 }).unwrap();
+    /// cache.insert(1, Handle { len: 2048 }).unwrap();
+    ///
+    /// assert_eq!(1, cache.len());
+    /// assert_eq!(2048, cache.current_size());
+    /// ```
+    pub fn with_weigher(max_weight: usize, weigher: W)
+            -> LruCache<K, V, DefaultHashBuilder, W> {
+        LruCache::with_table_hasher_and_weigher(max_weight, RawTable::new(),
+            DefaultHashBuilder::default(), weigher)
+    }
+}
+
+impl<K, V, S, W> LruCache<K, V, S, W> {
+
+    /// Creates a new, empty LRU cache with the given maximum weight which will
+    /// use the given hash builder to hash keys and the given [Weigher] to
+    /// determine the weight of each entry. See [LruCache::with_hasher] and
+    /// [LruCache::with_weigher] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_weight`: The maximum weight that the sum of the weights of all
+    ///   entries may reach.
+    /// * `hash_builder`: The hasher used to hash keys. It should implement the
+    ///   [BuildHasher] trait to allow operations being applied to the cache.
+    /// * `weigher`: The weigher used to determine the weight of each entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hashbrown::hash_map::DefaultHashBuilder;
+    /// use lru_mem::LruCache;
+    ///
+    /// // Create an LRU cache which holds at most 2 entries and uses s for
+    /// // hashing keys.
+    /// let s = DefaultHashBuilder::default();
+    /// let mut cache = LruCache::with_hasher_and_weigher(2, s,
+    ///     |_: &u32, _: &&str| 1);
+    /// cache.insert(0, "hello").unwrap();
+    /// cache.insert(1, "world").unwrap();
+    /// cache.insert(2, "moon").unwrap();
+    ///
+    /// assert_eq!(2, cache.len());
+    /// assert_eq!(2, cache.current_size());
+    /// ```
+    pub fn with_hasher_and_weigher(max_weight: usize, hash_builder: S,
+            weigher: W) -> LruCache<K, V, S, W> {
+        LruCache::with_table_hasher_and_weigher(max_weight, RawTable::new(),
+            hash_builder, weigher)
+    }
+
+    /// Creates a new, empty LRU cache with the given maximum weight and the
+    /// specified initial capacity which will use the given hash builder to
+    /// hash keys and the given [Weigher] to determine the weight of each
+    /// entry. See [LruCache::with_capacity_and_hasher] and
+    /// [LruCache::with_weigher] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_weight`: The maximum weight that the sum of the weights of all
+    ///   entries may reach.
+    /// * `capacity`: A lower bound on the number of elements that the cache
+    ///   will be able to hold without reallocating.
+    /// * `hash_builder`: The hasher used to hash keys. It should implement the
+    ///   [BuildHasher] trait to allow operations being applied to the cache.
+    /// * `weigher`: The weigher used to determine the weight of each entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hashbrown::hash_map::DefaultHashBuilder;
+    /// use lru_mem::LruCache;
+    ///
+    /// // Create an LRU cache which holds at most 16 entries, can hold at least
+    /// // 8 entries without reallocating, and uses s for hashing keys.
+    /// let s = DefaultHashBuilder::default();
+    /// let mut cache = LruCache::with_capacity_hasher_and_weigher(16, 8, s,
+    ///     |_: &u32, _: &String| 1);
+    /// cache.insert(0, "hello".to_owned()).unwrap();
+    ///
+    /// assert!(cache.capacity() >= 8);
+    /// assert_eq!(1, cache.current_size());
+    /// ```
+    pub fn with_capacity_hasher_and_weigher(max_weight: usize,
+            capacity: usize, hash_builder: S, weigher: W)
+            -> LruCache<K, V, S, W> {
+        LruCache::with_table_hasher_and_weigher(max_weight,
+            RawTable::with_capacity(capacity), hash_builder, weigher)
+    }
+
+    fn with_table_hasher_and_weigher(max_size: usize,
+            table: RawTable<Entry<K, V>>, hash_builder: S, weigher: W)
+            -> LruCache<K, V, S, W> {
+        let seal = EntryPtr::new_seal();
+
+        LruCache {
+            table,
+            meta: Vec::new(),
+            access_counter: 0,
+            expiration_used: false,
+            seal,
+            current_size: 0,
+            pinned_size: 0,
+            max_size,
+            hash_builder,
+            removal_listener: None,
+            default_ttl: None,
+            time_to_idle: None,
+            clock: None,
+            weigher
+        }
+    }
+
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    unsafe fn index_of(&self, entry: EntryPtr<K, V>) -> usize {
+        entry.index_in(&self.table)
+    }
+
+    fn meta_at(&self, index: usize) -> &EntryMeta {
+        self.meta.get(index).unwrap_or(&NO_META)
+    }
+
+    /// Allocates the metadata table if this has not happened yet.
+    fn meta_at_mut(&mut self, index: usize) -> &mut EntryMeta {
+        if self.meta.is_empty() {
+            self.meta = vec![EntryMeta::default(); self.table.buckets()];
+        }
+
+        &mut self.meta[index]
+    }
+
+    /// Unlike [LruCache::meta_at_mut], this does not allocate the metadata
+    /// table for default metadata.
+    fn set_meta_at(&mut self, index: usize, meta: EntryMeta) {
+        if !self.meta.is_empty() || meta != EntryMeta::default() {
+            *self.meta_at_mut(index) = meta;
+        }
+    }
+
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    unsafe fn meta(&self, entry: EntryPtr<K, V>) -> &EntryMeta {
+        self.meta_at(self.index_of(entry))
+    }
+
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    unsafe fn meta_mut(&mut self, entry: EntryPtr<K, V>) -> &mut EntryMeta {
+        self.meta_at_mut(self.index_of(entry))
+    }
+
+    /// Safety: Requires the bucket to be full.
+    unsafe fn bucket_meta(&self, bucket: &Bucket<Entry<K, V>>) -> &EntryMeta {
+        self.meta_at(self.table.bucket_index(bucket))
+    }
+
+    fn now(&self) -> Instant {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => Instant::now()
+        }
+    }
+
+    fn expiry_for(&mut self, ttl: Option<Duration>) -> Option<Instant> {
+        let expires_at = ttl.and_then(|ttl| self.now().checked_add(ttl));
+        self.expiration_used |= expires_at.is_some();
+        expires_at
+    }
+
+    /// Indicates whether any entry may be expired, i.e. a time-to-idle is set
+    /// or some entry was given an expiration time. Otherwise, lookups need
+    /// not check entries for expiry.
+    fn may_expire(&self) -> bool {
+        self.time_to_idle.is_some() || self.expiration_used
+    }
+
+    /// Pinned entries never expire. The clock is only queried for entries
+    /// that have an expiration time or may become idle.
+    fn is_expired(&self, meta: &EntryMeta) -> bool {
+        meta.pins == 0 && meta.deadline(self.time_to_idle)
+            .is_some_and(|deadline| deadline <= self.now())
+    }
+
+    /// Returns `None` if no entry can be expired (see [LruCache::may_expire]).
+    /// In that case, the clock is not queried.
+    fn expiry_check(&self) -> Option<ExpiryCheck<'_, K, V>> {
+        if !self.may_expire() {
+            None
+        }
+        else {
+            Some(ExpiryCheck::new(&self.table, &self.meta, self.time_to_idle,
+                self.now()))
+        }
+    }
+
+    /// Safety: Requires the bucket to be full.
+    unsafe fn is_bucket_expired(&self, bucket: &Bucket<Entry<K, V>>) -> bool {
+        self.is_expired(self.bucket_meta(bucket))
+    }
+
+    /// Every access gives the entry a new logical access stamp, by which
+    /// [LruCache::merge] orders entries. Entries are stamped with the current
+    /// time only while a time-to-idle is set, so the clock is not queried
+    /// otherwise.
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    unsafe fn record_access(&mut self, entry: EntryPtr<K, V>) {
+        let now = self.time_to_idle.map(|_| self.now());
+        self.access_counter += 1;
+        let access_stamp = self.access_counter;
+        let meta = self.meta_mut(entry);
+        meta.access_stamp = access_stamp;
+
+        if now.is_some() {
+            meta.last_access = now;
+        }
+    }
+
+    /// Entries moved in the usage history without being accessed could end
+    /// up behind entries which were accessed later. To keep the entries
+    /// ordered by their last access, which allows [LruCache::remove_idle] to
+    /// stop at the first entry that is not idle and [LruCache::merge] to
+    /// interleave two lists in one pass, the access stamp and time of the
+    /// moved entry are clamped between those of its new neighbors.
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    unsafe fn align_access(&mut self, entry: EntryPtr<K, V>) {
+        let meta_of = |neighbor: EntryPtr<K, V>| {
+            if neighbor == self.seal {
+                None
+            }
+            else {
+                Some(*self.meta(neighbor))
+            }
+        };
+        let newer = meta_of(entry.get().prev);
+        let older = meta_of(entry.get().next);
+        let track_idle_time = self.time_to_idle.is_some();
+        let meta = self.meta_mut(entry);
+
+        if let Some(newer) = &newer {
+            meta.access_stamp = meta.access_stamp.min(newer.access_stamp);
+        }
+
+        if let Some(older) = &older {
+            meta.access_stamp = meta.access_stamp.max(older.access_stamp);
+        }
+
+        if track_idle_time {
+            let newer_access = newer.and_then(|newer| newer.last_access);
+            let older_access = older.and_then(|older| older.last_access);
+
+            meta.last_access = meta.last_access
+                .map(|access| newer_access.map_or(access, |n| access.min(n)))
+                .map(|access| older_access.map_or(access, |o| access.max(o)));
+        }
+    }
+
+    /// Passes an entry dropped by this cache to the removal listener, if any.
+    /// Otherwise, the entry is dropped.
+    #[inline]
+    fn notify_removal(&mut self, key: K, value: V, cause: RemovalCause) {
+        if let Some(listener) = &mut self.removal_listener {
+            listener(key, value, cause);
+        }
+    }
+
+    /// Used as `on_eject` wherever ejected entries are not handed to the
+    /// caller, so they are reported to the removal listener.
+    fn drop_ejected(&mut self, key: K, value: V) {
+        self.notify_removal(key, value, RemovalCause::Ejected);
+    }
+
+    /// Reports the entry of a [MutateError] which is not returned to the
+    /// caller to the removal listener.
+    fn drop_too_large(&mut self, err: MutateError<K, V>) {
+        let (key, value) = match err {
+            MutateError::EntryTooLarge { key, value, .. } => (key, value),
+            MutateError::WouldEjectPinned { key, value, .. } => (key, value)
+        };
+
+        self.notify_removal(key, value, RemovalCause::EntryTooLarge);
     }
 
     /// Gets the maximum number of bytes that the sum of the memory estimates
@@ -283,7 +710,10 @@ impl<K, V, S> LruCache<K, V, S> {
     }
 
     /// Gets the current estimated memory of all entries contained in this
-    /// cache, in bytes.
+    /// cache, in bytes. This includes expired entries (see
+    /// [LruCache::insert_with_ttl] and [LruCache::set_time_to_idle]) until
+    /// they are removed, e.g. by [LruCache::purge_expired] or a lookup that
+    /// finds them, such as [LruCache::get].
     ///
     /// # Example
     ///
@@ -302,7 +732,40 @@ impl<K, V, S> LruCache<K, V, S> {
         self.current_size
     }
 
-    /// Gets the number of entries contained in this cache.
+    /// Gets the current estimated memory of all pinned entries contained in
+    /// this cache, in bytes (see [LruCache::pin]). This memory is not
+    /// available for entries that can be ejected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// assert_eq!(0, cache.pinned_size());
+    ///
+    /// cache.pin("apple");
+    /// assert!(cache.pinned_size() > 0);
+    /// assert!(cache.pinned_size() < cache.current_size());
+    /// ```
+    pub fn pinned_size(&self) -> usize {
+        self.pinned_size
+    }
+
+    /// Gets the memory of this cache that is not occupied by pinned entries,
+    /// i.e. the maximum size an entry may have to fit in the cache if all
+    /// unpinned entries were ejected.
+    fn available_memory(&self) -> usize {
+        self.max_size.saturating_sub(self.pinned_size)
+    }
+
+    /// Gets the number of entries contained in this cache. This includes
+    /// expired entries (see [LruCache::insert_with_ttl] and
+    /// [LruCache::set_time_to_idle]) until they are removed, e.g. by
+    /// [LruCache::purge_expired] or a lookup that finds them, such as
+    /// [LruCache::get].
     ///
     /// # Example
     ///
@@ -369,38 +832,56 @@ impl<K, V, S> LruCache<K, V, S> {
         &self.hash_builder
     }
 
-    /// Removes all elements from this cache.
+    /// Registers a listener which is called for every entry that this cache
+    /// drops on its own, together with the [RemovalCause]. It takes ownership
+    /// of the key and value of the entry, so they can be flushed or recycled
+    /// instead of being dropped. Any previously registered listener is
+    /// replaced.
+    ///
+    /// Entries which are handed back to the caller are not reported. This
+    /// includes entries removed with methods such as [LruCache::remove],
+    /// values replaced by [LruCache::insert], entries returned in the error of
+    /// [LruCache::mutate], and entries yielded by iterators such as
+    /// [LruCache::drain] or [LruCache::resize]. Entries which such an iterator
+    /// drops because it is dropped before being exhausted are reported.
+    /// Clones of this cache do not inherit the listener.
+    ///
+    /// # Arguments
+    ///
+    /// * `listener`: The function to call with the key, value, and cause of
+    ///   each entry dropped by this cache.
     ///
     /// # Example
     ///
     /// ```
-    /// use lru_mem::LruCache;
+    /// use lru_mem::{LruCache, RemovalCause};
+    /// use std::sync::{Arc, Mutex};
     ///
+    /// let ejected = Arc::new(Mutex::new(Vec::new()));
+    /// let ejected_clone = Arc::clone(&ejected);
     /// let mut cache = LruCache::new(1024);
+    /// cache.set_removal_listener(move |key: String, _: String, cause| {
+    ///     if cause == RemovalCause::Ejected {
+    ///         ejected_clone.lock().unwrap().push(key);
+    ///     }
+    /// });
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    /// cache.clear();
+    /// cache.set_max_size(cache.current_size());
+    /// cache.insert("peach".to_owned(), "sweet".to_owned()).unwrap();
     ///
-    /// assert_eq!(None, cache.get("lemon"));
-    /// assert_eq!(0, cache.len());
-    /// assert_eq!(0, cache.current_size());
+    /// assert_eq!(vec!["apple".to_owned()], *ejected.lock().unwrap());
     /// ```
-    pub fn clear(&mut self) {
-        for entry in self.table.drain() {
-            unsafe { entry.drop(); }
-        }
-
-        self.current_size = 0;
-        self.seal.get_mut().next = self.seal;
-        self.seal.get_mut().prev = self.seal;
+    pub fn set_removal_listener<F>(&mut self, listener: F)
+    where
+        F: FnMut(K, V, RemovalCause) + Send + 'static
+    {
+        self.removal_listener = Some(Box::new(listener));
     }
 
-    /// Creates an iterator over the entries (keys and values) contained in
-    /// this cache, ordered from least- to most-recently-used. The values are
-    /// not touched, i.e. the usage history is not altered in any way. That is,
-    /// the semantics are as in [LruCache::peek].
-    ///
-    /// The memory requirement for any key or value may not be changed.
+    /// Unregisters the listener previously registered with
+    /// [LruCache::set_removal_listener], if any. Afterwards, dropped entries
+    /// are no longer reported.
     ///
     /// # Example
     ///
@@ -408,74 +889,301 @@ impl<K, V, S> LruCache<K, V, S> {
     /// use lru_mem::LruCache;
     ///
     /// let mut cache = LruCache::new(1024);
+    /// cache.set_removal_listener(|_: String, _: String, _| {
+    ///     panic!("listener was called");
+    /// });
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
-    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    /// cache.insert("grapefruit".to_owned(), "bitter".to_owned()).unwrap();
-    /// let mut iter = cache.iter();
-    ///
-    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
-    ///     iter.next());
-    /// assert_eq!(Some((&"grapefruit".to_owned(), &"bitter".to_owned())),
-    ///     iter.next_back());
-    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
-    ///     iter.next());
-    /// assert_eq!(None, iter.next());
-    /// assert_eq!(None, iter.next_back());
+    /// cache.clear_removal_listener();
+    /// cache.clear();
     /// ```
-    pub fn iter(&self) -> Iter<'_, K, V> {
-        Iter::new(self)
+    pub fn clear_removal_listener(&mut self) {
+        self.removal_listener = None;
     }
 
-    /// Creates an iterator over the keys contained in this cache, ordered from
-    /// least- to most-recently-used. The values are not touched, i.e. the
-    /// usage history is not altered in any way. That is, the semantics are as
-    /// in [LruCache::peek].
-    ///
-    /// The memory requirement for any key may not be changed.
+    /// Gets the time-to-live that is assigned to entries inserted without an
+    /// explicit one, or `None` if such entries never expire. This can be
+    /// changed with [LruCache::set_default_ttl].
     ///
     /// # Example
     ///
     /// ```
     /// use lru_mem::LruCache;
+    /// use std::time::Duration;
     ///
-    /// let mut cache = LruCache::new(1024);
-    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
-    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    /// cache.insert("grapefruit".to_owned(), "bitter".to_owned()).unwrap();
-    /// let mut keys = cache.keys();
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    /// assert_eq!(None, cache.default_ttl());
     ///
-    /// assert_eq!(Some(&"apple".to_owned()), keys.next());
-    /// assert_eq!(Some(&"grapefruit".to_owned()), keys.next_back());
-    /// assert_eq!(Some(&"lemon".to_owned()), keys.next());
-    /// assert_eq!(None, keys.next());
-    /// assert_eq!(None, keys.next_back());
+    /// cache.set_default_ttl(Some(Duration::from_secs(60)));
+    /// assert_eq!(Some(Duration::from_secs(60)), cache.default_ttl());
     /// ```
-    pub fn keys(&self) -> Keys<'_, K, V> {
-        Keys::new(self)
+    pub fn default_ttl(&self) -> Option<Duration> {
+        self.default_ttl
     }
 
-    /// Creates an iterator over the values contained in this cache, ordered
-    /// from least- to most-recently-used. The values are not touched, i.e. the
-    /// usage history is not altered in any way. That is, the semantics are as
-    /// in [LruCache::peek].
+    /// Sets the time-to-live that is assigned to entries inserted without an
+    /// explicit one, e.g. by [LruCache::insert], [LruCache::try_insert], or
+    /// the [LruCache::entry] API. Entries that are already in the cache keep
+    /// their expiration time. See [LruCache::insert_with_ttl] for details on
+    /// how expired entries are treated.
     ///
-    /// The memory requirement for any value may not be changed.
+    /// # Arguments
+    ///
+    /// * `ttl`: The duration after which newly inserted entries expire, or
+    ///   `None` if they shall never expire.
     ///
     /// # Example
     ///
     /// ```
     /// use lru_mem::LruCache;
+    /// use std::time::Duration;
     ///
     /// let mut cache = LruCache::new(1024);
+    /// cache.set_default_ttl(Some(Duration::ZERO));
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
-    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    /// cache.insert("grapefruit".to_owned(), "bitter".to_owned()).unwrap();
-    /// let mut values = cache.values();
     ///
-    /// assert_eq!(Some(&"sweet".to_owned()), values.next());
-    /// assert_eq!(Some(&"bitter".to_owned()), values.next_back());
-    /// assert_eq!(Some(&"sour".to_owned()), values.next());
-    /// assert_eq!(None, values.next());
+    /// assert!(!cache.contains("apple"));
+    /// ```
+    pub fn set_default_ttl(&mut self, ttl: Option<Duration>) {
+        self.default_ttl = ttl;
+    }
+
+    /// Gets the duration after which entries expire if they are not accessed,
+    /// or `None` if entries never become idle. This can be changed with
+    /// [LruCache::set_time_to_idle].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    /// assert_eq!(None, cache.time_to_idle());
+    ///
+    /// cache.set_time_to_idle(Some(Duration::from_secs(60)));
+    /// assert_eq!(Some(Duration::from_secs(60)), cache.time_to_idle());
+    /// ```
+    pub fn time_to_idle(&self) -> Option<Duration> {
+        self.time_to_idle
+    }
+
+    /// Sets the duration after which entries expire if they are not accessed.
+    /// Every operation that marks an entry as most-recently-used, such as
+    /// inserting it, [LruCache::get], [LruCache::touch], or
+    /// [LruCache::mutate], resets its idle time. Operations that do not alter
+    /// the usage history, such as [LruCache::peek], do not.
+    ///
+    /// Idle entries are treated just like entries whose time-to-live elapsed
+    /// (see [LruCache::insert_with_ttl]). Since the least-recently-used entry
+    /// is always the one that has been idle the longest, they can be removed
+    /// efficiently with [LruCache::remove_idle].
+    ///
+    /// When enabling the time-to-idle, the idle time of all entries starts
+    /// now, which requires O(n) time, where n is the number of entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_to_idle`: The duration after which entries that are not
+    ///   accessed expire, or `None` if entries shall never become idle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.set_time_to_idle(Some(Duration::ZERO));
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert!(!cache.contains("apple"));
+    /// ```
+    pub fn set_time_to_idle(&mut self, time_to_idle: Option<Duration>) {
+        if self.time_to_idle.is_none() && time_to_idle.is_some() {
+            let now = self.now();
+            let mut tail = self.seal.get().prev;
+
+            while tail != self.seal {
+                unsafe { self.meta_mut(tail).last_access = Some(now); }
+                tail = tail.get().prev;
+            }
+        }
+
+        self.time_to_idle = time_to_idle;
+    }
+
+    /// Sets the [Clock] which is used to determine the current time when
+    /// assigning expiration times to entries and checking whether they are
+    /// expired. By default, the [SystemClock] is used. Expiration times of
+    /// entries already in the cache are not adjusted, so the clock should
+    /// usually be set before inserting any entries. Clones of this cache share
+    /// the clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `clock`: The clock to use from now on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{Clock, LruCache};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// struct FrozenClock(Instant);
+    ///
+    /// impl Clock for FrozenClock {
+    ///     fn now(&self) -> Instant {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.set_clock(FrozenClock(Instant::now()));
+    /// cache.insert_with_ttl("apple".to_owned(), "sweet".to_owned(),
+    ///     Duration::from_nanos(1)).unwrap();
+    ///
+    /// // Time never passes, so the entry does not expire.
+    /// assert!(cache.contains("apple"));
+    /// ```
+    pub fn set_clock<C>(&mut self, clock: C)
+    where
+        C: Clock + 'static
+    {
+        self.clock = Some(Arc::new(clock));
+    }
+
+    /// Removes all elements from this cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.clear();
+    ///
+    /// assert_eq!(None, cache.get("lemon"));
+    /// assert_eq!(0, cache.len());
+    /// assert_eq!(0, cache.current_size());
+    /// ```
+    pub fn clear(&mut self) {
+        let mut tail = self.seal.get().prev;
+
+        if self.removal_listener.is_some() {
+            self.table.clear_no_drop();
+        }
+        else {
+            for entry in self.table.drain() {
+                unsafe { entry.drop(); }
+            }
+        }
+
+        self.current_size = 0;
+        self.pinned_size = 0;
+        self.seal.get_mut().next = self.seal;
+        self.seal.get_mut().prev = self.seal;
+
+        if self.removal_listener.is_some() {
+            // The cache is already empty, so a panicking listener leaks the
+            // remaining entries instead of leaving moved-out ones behind. The
+            // entries stay readable until new ones are inserted.
+
+            while tail != self.seal {
+                unsafe {
+                    let entry = tail.read();
+                    tail = entry.prev;
+                    let (key, value) = entry.into_key_value();
+                    self.notify_removal(key, value, RemovalCause::Cleared);
+                }
+            }
+        }
+    }
+
+    /// Creates an iterator over the entries (keys and values) contained in
+    /// this cache, ordered from least- to most-recently-used. The values are
+    /// not touched, i.e. the usage history is not altered in any way. That is,
+    /// the semantics are as in [LruCache::peek]. In particular, expired
+    /// entries (see [LruCache::insert_with_ttl]) are skipped.
+    ///
+    /// The memory requirement for any key or value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("grapefruit".to_owned(), "bitter".to_owned()).unwrap();
+    /// let mut iter = cache.iter();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     iter.next());
+    /// assert_eq!(Some((&"grapefruit".to_owned(), &"bitter".to_owned())),
+    ///     iter.next_back());
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     iter.next());
+    /// assert_eq!(None, iter.next());
+    /// assert_eq!(None, iter.next_back());
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(self)
+    }
+
+    /// Creates an iterator over the keys contained in this cache, ordered from
+    /// least- to most-recently-used. The values are not touched, i.e. the
+    /// usage history is not altered in any way. That is, the semantics are as
+    /// in [LruCache::peek]. In particular, keys of expired entries are skipped.
+    ///
+    /// The memory requirement for any key may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("grapefruit".to_owned(), "bitter".to_owned()).unwrap();
+    /// let mut keys = cache.keys();
+    ///
+    /// assert_eq!(Some(&"apple".to_owned()), keys.next());
+    /// assert_eq!(Some(&"grapefruit".to_owned()), keys.next_back());
+    /// assert_eq!(Some(&"lemon".to_owned()), keys.next());
+    /// assert_eq!(None, keys.next());
+    /// assert_eq!(None, keys.next_back());
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys::new(self)
+    }
+
+    /// Creates an iterator over the values contained in this cache, ordered
+    /// from least- to most-recently-used. The values are not touched, i.e. the
+    /// usage history is not altered in any way. That is, the semantics are as
+    /// in [LruCache::peek]. In particular, values of expired entries are
+    /// skipped.
+    ///
+    /// The memory requirement for any value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("grapefruit".to_owned(), "bitter".to_owned()).unwrap();
+    /// let mut values = cache.values();
+    ///
+    /// assert_eq!(Some(&"sweet".to_owned()), values.next());
+    /// assert_eq!(Some(&"bitter".to_owned()), values.next_back());
+    /// assert_eq!(Some(&"sour".to_owned()), values.next());
+    /// assert_eq!(None, values.next());
     /// assert_eq!(None, values.next_back());
     /// ```
     pub fn values(&self) -> Values<'_, K, V> {
@@ -506,7 +1214,7 @@ impl<K, V, S> LruCache<K, V, S> {
     /// assert_eq!(&("grapefruit".to_owned(), "bitter".to_owned()), &vec[2]);
     /// assert!(cache.is_empty());
     /// ```
-    pub fn drain(&mut self) -> Drain<'_, K, V, S> {
+    pub fn drain(&mut self) -> Drain<'_, K, V, S, W> {
         Drain::new(self)
     }
 
@@ -528,7 +1236,7 @@ impl<K, V, S> LruCache<K, V, S> {
     /// assert_eq!(&"lemon".to_owned(), &keys[1]);
     /// assert_eq!(&"grapefruit".to_owned(), &keys[2]);
     /// ```
-    pub fn into_keys(self) -> IntoKeys<K, V, S> {
+    pub fn into_keys(self) -> IntoKeys<K, V, S, W> {
         IntoKeys::new(self)
     }
 
@@ -550,7 +1258,7 @@ impl<K, V, S> LruCache<K, V, S> {
     /// assert_eq!(&"sour".to_owned(), &values[1]);
     /// assert_eq!(&"bitter".to_owned(), &values[2]);
     /// ```
-    pub fn into_values(self) -> IntoValues<K, V, S> {
+    pub fn into_values(self) -> IntoValues<K, V, S, W> {
         IntoValues::new(self)
     }
 }
@@ -587,49 +1295,90 @@ where
 
 fn equivalent_key<Q, K, V>(k: &Q) -> impl Fn(&Entry<K, V>) -> bool + '_
 where
-    K: Borrow<Q>,
-    Q: ?Sized + Eq,
+    Q: ?Sized + Equivalent<K>,
 {
-    move |x| k.eq(unsafe { x.key() }.borrow())
+    move |x| k.equivalent(unsafe { x.key() })
 }
 
-impl<K, V, S> LruCache<K, V, S>
+impl<K, V, S, W> LruCache<K, V, S, W>
 where
     K: Eq + Hash,
     S: BuildHasher
 {
-    fn remove_from_table<Q>(&mut self, key: &Q) -> Option<Entry<K, V>>
+    /// Expired entries are treated as absent, but remain in the table.
+    fn get_from_table<Q>(&self, key: &Q) -> Option<&Entry<K, V>>
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
         let hash = make_hash::<Q, S>(&self.hash_builder, key);
-        self.table.remove_entry(hash, equivalent_key(key))
+        let bucket = self.table.find(hash, equivalent_key(key))?;
+
+        unsafe {
+            if self.is_bucket_expired(&bucket) {
+                None
+            }
+            else {
+                Some(bucket.as_ref())
+            }
+        }
     }
 
-    fn get_from_table<Q>(&self, key: &Q) -> Option<&Entry<K, V>>
+    fn get_ptr_from_table<Q>(&self, key: &Q) -> Option<EntryPtr<K, V>>
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
         let hash = make_hash::<Q, S>(&self.hash_builder, key);
-        self.table.get(hash, equivalent_key(key))
+        let bucket = self.table.find(hash, equivalent_key(key))?;
+
+        unsafe {
+            if self.is_bucket_expired(&bucket) {
+                None
+            }
+            else {
+                Some(EntryPtr::new(bucket.as_ptr()))
+            }
+        }
     }
 
-    fn get_mut_from_table<Q>(&mut self, key: &Q) -> Option<&mut Entry<K, V>>
+    /// Expired entries are removed from the table and treated as absent.
+    fn get_live_ptr<Q>(&mut self, key: &Q) -> Option<EntryPtr<K, V>>
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
         let hash = make_hash::<Q, S>(&self.hash_builder, key);
-        self.table.get_mut(hash, equivalent_key(key))
+        let bucket = self.table.find(hash, equivalent_key(key))?;
+
+        unsafe {
+            if self.is_bucket_expired(&bucket) {
+                self.remove_bucket(bucket, RemovalCause::Expired);
+                None
+            }
+            else {
+                Some(EntryPtr::new(bucket.as_ptr()))
+            }
+        }
+    }
+
+    /// Removes the entry in the given bucket and reports it to the removal
+    /// listener with the given cause.
+    ///
+    /// Safety: Requires the bucket to be full and its entry to have an
+    /// initialized key and value.
+    unsafe fn remove_bucket(&mut self, bucket: Bucket<Entry<K, V>>,
+            cause: RemovalCause) {
+        let (key, value) = self.take_bucket(bucket);
+        self.notify_removal(key, value, cause);
     }
 
     #[inline]
-    fn insert_into_table_with_hash(&mut self, hash: u64, entry: Entry<K, V>)
-            -> Result<EntryPtr<K, V>, Entry<K, V>> {
+    fn insert_into_table_with_hash(&mut self, hash: u64, entry: Entry<K, V>,
+            meta: EntryMeta) -> Result<EntryPtr<K, V>, Entry<K, V>> {
         match self.table.try_insert_no_grow(hash, entry) {
-            Ok(bucket) => Ok(EntryPtr::new(bucket.as_ptr())),
+            Ok(bucket) => {
+                let index = unsafe { self.table.bucket_index(&bucket) };
+                self.set_meta_at(index, meta);
+                Ok(EntryPtr::new(bucket.as_ptr()))
+            },
             Err(entry) => Err(entry)
         }
     }
@@ -637,49 +1386,178 @@ where
     /// Assumes that there is no entry with the same key in the table. If
     /// insertion works, returns a pointer to the entry inside the table.
     /// Otherwise, returns the entry input into this function.
-    fn insert_into_table(&mut self, entry: Entry<K, V>)
+    fn insert_into_table(&mut self, entry: Entry<K, V>, meta: EntryMeta)
             -> Result<EntryPtr<K, V>, Entry<K, V>> {
         let key = unsafe { entry.key() };
         let hash = make_insert_hash::<K, S>(&self.hash_builder, key);
 
-        self.insert_into_table_with_hash(hash, entry)
+        self.insert_into_table_with_hash(hash, entry, meta)
     }
 
     fn set_head(&mut self, mut entry: EntryPtr<K, V>) {
         entry.insert(self.seal, self.seal.get().next);
     }
 
+    fn set_tail(&mut self, mut entry: EntryPtr<K, V>) {
+        entry.insert(self.seal.get().prev, self.seal);
+    }
+
     fn touch_ptr(&mut self, entry: EntryPtr<K, V>) {
-        unsafe { entry.unhinge(); }
-        self.set_head(entry);
+        unsafe {
+            entry.unhinge();
+            self.set_head(entry);
+            self.record_access(entry);
+        }
     }
 
     /// Safety: Requires the key and value of the entry to be initialized.
-    unsafe fn remove_metadata(&mut self, entry: Entry<K, V>) -> (K, V) {
+    unsafe fn remove_metadata(&mut self, entry: Entry<K, V>, pins: usize)
+            -> (K, V) {
+        if pins > 0 {
+            self.pinned_size -= entry.size;
+        }
+
         let entry = entry.unhinge();
         self.current_size -= entry.size();
 
         entry.into_key_value()
     }
 
-    /// Safety: Requires the key of the entry pointed to by the pointer to be
-    /// initialized, and the key and value of the entry located at that key in
-    /// the hash table to be initialized.
+    /// Removes the entry in the given bucket from the table and the list and
+    /// updates the size of this cache, without notifying the removal listener.
+    ///
+    /// Safety: Requires the bucket to be full and its entry to have an
+    /// initialized key and value.
+    unsafe fn take_bucket(&mut self, bucket: Bucket<Entry<K, V>>) -> (K, V) {
+        let pins = self.bucket_meta(&bucket).pins;
+        let (entry, _) = self.table.remove(bucket);
+        self.remove_metadata(entry, pins)
+    }
+
+    /// Unlike [LruCache::touch_ptr], this does not count as an access, so the
+    /// idle time of the entry is not reset.
+    fn demote_ptr(&mut self, entry: EntryPtr<K, V>) {
+        unsafe {
+            entry.unhinge();
+            self.set_tail(entry);
+            self.align_access(entry);
+        }
+    }
+
+    /// Gets the bucket in which the entry pointed to by the given pointer is
+    /// stored. Unlike a lookup by key, this identifies the entry even if the
+    /// table contains another entry with an equal key, which may happen if
+    /// keys were inserted with a wrong hash via [LruCache::raw_entry_mut].
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table.
+    unsafe fn bucket_of(&self, entry: EntryPtr<K, V>) -> Bucket<Entry<K, V>> {
+        self.table.bucket(self.index_of(entry))
+    }
+
+    /// Safety: Requires the pointer to point to an entry contained in the
+    /// table, whose key and value are initialized.
     #[inline]
     unsafe fn remove_ptr(&mut self, entry: EntryPtr<K, V>) -> (K, V) {
-        let entry = self.remove_from_table(entry.get().key()).unwrap();
-        self.remove_metadata(entry)
+        self.take_bucket(self.bucket_of(entry))
+    }
+
+    /// Ejects entries as in `eject_to_target_with`, reporting them to the
+    /// removal listener with the given cause.
+    fn eject_to_target(&mut self, target: usize, cause: RemovalCause) {
+        self.eject_to_target_with(target, |cache, key, value|
+            cache.notify_removal(key, value, cause))
+    }
+
+    /// Ejects least-recently-used unpinned entries until the current size is
+    /// at most `target`, passing each ejected entry to `on_eject` in LRU
+    /// order together with this cache. Entries are only reported to the
+    /// removal listener if `on_eject` does so (see
+    /// [LruCache::drop_ejected]). If only pinned entries remain, the target
+    /// may not be reached.
+    fn eject_to_target_with<F>(&mut self, target: usize, on_eject: F)
+    where
+        F: FnMut(&mut Self, K, V)
+    {
+        self.eject_to_target_sparing(target, self.seal, on_eject)
+    }
+
+    /// Ejects least-recently-used unpinned entries as in
+    /// `eject_to_target_with`, but never the entry pointed to by `spared`.
+    fn eject_to_target_sparing<F>(&mut self, target: usize,
+        spared: EntryPtr<K, V>, mut on_eject: F)
+    where
+        F: FnMut(&mut Self, K, V)
+    {
+        let mut tail = self.seal.get().prev;
+
+        while self.current_size > target && tail != self.seal {
+            let prev = tail.get().prev;
+
+            if tail != spared && unsafe { self.meta(tail).pins } == 0 {
+                let (key, value) = unsafe { self.remove_ptr(tail) };
+                on_eject(self, key, value);
+            }
+
+            tail = prev;
+        }
+    }
+
+    /// Computes the number of bytes that ejecting entries until the current
+    /// size is at most `target` would free, skipping pinned entries as in
+    /// `eject_to_target_with`.
+    fn ejected_size(&self, target: usize) -> usize {
+        let mut freed = 0;
+        let mut tail = self.seal.get().prev;
+
+        while self.current_size - freed > target && tail != self.seal {
+            let entry = tail.get();
+
+            if unsafe { self.meta(tail).pins } == 0 {
+                freed += entry.size;
+            }
+
+            tail = entry.prev;
+        }
+
+        freed
+    }
+
+    fn lru_unpinned_ptr(&self) -> Option<EntryPtr<K, V>> {
+        let mut tail = self.seal.get().prev;
+
+        while tail != self.seal && unsafe { self.meta(tail).pins } > 0 {
+            tail = tail.get().prev;
+        }
+
+        if tail == self.seal {
+            None
+        }
+        else {
+            Some(tail)
+        }
     }
 
-    fn eject_to_target(&mut self, target: usize) {
-        while self.current_size > target {
-            self.remove_lru();
+    /// Removes expired entries at the least-recently-used end of the list
+    /// until a live one is found, which is returned.
+    fn live_lru_ptr(&mut self) -> Option<EntryPtr<K, V>> {
+        loop {
+            let lru = self.lru_ptr()?;
+
+            unsafe {
+                if !self.is_expired(self.meta(lru)) {
+                    return Some(lru);
+                }
+
+                let (key, value) = self.remove_ptr(lru);
+                self.notify_removal(key, value, RemovalCause::Expired);
+            }
         }
     }
 
-    fn insert_untracked(&mut self, entry: Entry<K, V>) {
+    fn insert_untracked(&mut self, entry: Entry<K, V>, meta: EntryMeta) {
         let entry_ptr = unsafe {
-            self.insert_into_table(entry).unwrap_unchecked()
+            self.insert_into_table(entry, meta).unwrap_unchecked()
         };
         self.set_head(entry_ptr);
     }
@@ -688,14 +1566,31 @@ where
         let hasher = make_hasher(&self.hash_builder);
         let mut old_table = RawTable::try_with_capacity(new_capacity)?;
         mem::swap(&mut self.table, &mut old_table);
+        let old_meta = mem::take(&mut self.meta);
+
+        if !old_meta.is_empty() {
+            self.meta = vec![EntryMeta::default(); self.table.buckets()];
+        }
+
+        unsafe {
+            for old_bucket in old_table.iter() {
+                let old_index = old_table.bucket_index(&old_bucket);
+                let entry = EntryPtr::new(old_bucket.as_ptr()).read();
+                let mut prev_entry = entry.prev;
+                let mut next_entry = entry.next;
+                let bucket = self.table.insert(hasher(&entry), entry, &hasher);
+
+                if !old_meta.is_empty() {
+                    let index = self.table.bucket_index(&bucket);
+                    self.meta[index] = old_meta[old_index];
+                }
+
+                let entry_ptr = EntryPtr::new(bucket.as_ptr());
+                prev_entry.get_mut().next = entry_ptr;
+                next_entry.get_mut().prev = entry_ptr;
+            }
 
-        for entry in old_table.into_iter() {
-            let mut prev_entry = entry.prev;
-            let mut next_entry = entry.next;
-            let bucket = self.table.insert(hasher(&entry), entry, &hasher);
-            let entry_ptr = EntryPtr::new(bucket.as_ptr());
-            prev_entry.get_mut().next = entry_ptr;
-            next_entry.get_mut().prev = entry_ptr;
+            old_table.clear_no_drop();
         }
 
         Ok(())
@@ -727,9 +1622,14 @@ where
         }
     }
 
-    /// Removes the least-recently-used value from this cache. This returns
-    /// both key and value of the removed value. If this cache is empty, `None`
-    /// is returned.
+    /// Removes the least-recently-used unpinned value from this cache. This
+    /// returns both key and value of the removed value. If this cache contains
+    /// no unpinned entries, `None` is returned.
+    ///
+    /// Pinned entries (see [LruCache::pin]) are skipped. Hence, the removed
+    /// entry is not necessarily the least-recently-used one, and `None` may be
+    /// returned even if this cache is not empty, namely if all of its entries
+    /// are pinned.
     ///
     /// # Example
     ///
@@ -739,13 +1639,19 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("banana".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.pin("apple");
     ///
-    /// assert_eq!(Some(("apple".to_owned(), "sweet".to_owned())),
+    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
     ///     cache.remove_lru());
-    /// assert_eq!(1, cache.len());
+    ///
+    /// cache.pin("banana");
+    ///
+    /// assert_eq!(None, cache.remove_lru());
+    /// assert_eq!(2, cache.len());
     /// ```
     pub fn remove_lru(&mut self) -> Option<(K, V)> {
-        self.lru_ptr().map(|ptr| unsafe { self.remove_ptr(ptr) })
+        unsafe { Some(self.remove_ptr(self.lru_unpinned_ptr()?)) }
     }
 
     /// Gets a reference to the least-recently-used entry from this cache. This
@@ -754,6 +1660,8 @@ where
     ///
     /// This method also marks the value as most-recently-used. If you want the
     /// usage history to not be updated, use [LruCache::peek_lru] instead.
+    /// Expired entries (see [LruCache::insert_with_ttl]) at the
+    /// least-recently-used end are removed rather than returned.
     ///
     /// The memory requirement of the key and value may not be changed.
     ///
@@ -772,7 +1680,7 @@ where
     ///     cache.get_lru());
     /// ```
     pub fn get_lru(&mut self) -> Option<(&K, &V)> {
-        self.lru_ptr().map(|entry| {
+        self.live_lru_ptr().map(|entry| {
             unsafe {
                 self.touch_ptr(entry);
                 let entry = entry.get_extended();
@@ -787,6 +1695,8 @@ where
     ///
     /// This method does not mark the value as most-recently-used. If you want
     /// the usage history to be updated, use [LruCache::get_lru] instead.
+    /// Expired entries (see [LruCache::insert_with_ttl]) are skipped, but
+    /// remain in the cache.
     ///
     /// The memory requirement of the key and value may not be changed.
     ///
@@ -805,11 +1715,7 @@ where
     ///     cache.peek_lru());
     /// ```
     pub fn peek_lru(&self) -> Option<(&K, &V)> {
-        self.lru_ptr().map(|ptr|
-            unsafe {
-                let entry = ptr.get_extended();
-                (entry.key(), entry.value())
-            })
+        self.iter().next()
     }
 
     /// Removes the most-recently-used value from the cache. This returns both
@@ -830,7 +1736,7 @@ where
     /// assert_eq!(1, cache.len());
     /// ```
     pub fn remove_mru(&mut self) -> Option<(K, V)> {
-        self.mru_ptr().map(|ptr| unsafe { self.remove_ptr(ptr) })
+        unsafe { Some(self.remove_ptr(self.mru_ptr()?)) }
     }
 
     /// Gets a reference to the most-recently-used entry from this cache. This
@@ -839,7 +1745,8 @@ where
     ///
     /// Note that, for the most-recently-used entry, it does not matter whether
     /// the usage history is updated, since it was most-recently-used before.
-    /// So, there is no need for a `get_mru` method.
+    /// So, there is no need for a `get_mru` method. Expired entries (see
+    /// [LruCache::insert_with_ttl]) are skipped, but remain in the cache.
     ///
     /// The memory requirement of the key and value may not be changed.
     ///
@@ -856,13 +1763,188 @@ where
     ///     cache.peek_mru());
     /// ```
     pub fn peek_mru(&self) -> Option<(&K, &V)> {
-        self.mru_ptr().map(|ptr|
+        self.iter().next_back()
+    }
+
+    /// Gets a pointer to the entry which has exactly `n` entries less recently
+    /// used than it, walking from the closer end of the list.
+    fn nth_lru_ptr(&self, n: usize) -> Option<EntryPtr<K, V>> {
+        let len = self.len();
+
+        if n >= len {
+            return None;
+        }
+
+        let entry = if n < len / 2 {
+            let mut entry = self.seal.get().prev;
+
+            for _ in 0..n {
+                entry = entry.get().prev;
+            }
+
+            entry
+        }
+        else {
+            let mut entry = self.seal.get().next;
+
+            for _ in 0..(len - 1 - n) {
+                entry = entry.get().next;
+            }
+
+            entry
+        };
+
+        Some(entry)
+    }
+
+    /// Gets a reference to the `n`-th least-recently-used entry of this
+    /// cache, where the least-recently-used entry is at index 0. This returns
+    /// both key and value of the entry. If the cache contains no more than `n`
+    /// entries, `None` is returned.
+    ///
+    /// This method does not mark the value as most-recently-used. It takes
+    /// time linear in the distance of the entry from the closer end of the
+    /// usage history, i.e. at most half the number of entries. Expired
+    /// entries (see [LruCache::insert_with_ttl]) are skipped and not counted.
+    /// Once expiration times or a time-to-idle have been used, this requires
+    /// walking from the counted end, taking time linear in `n`.
+    ///
+    /// The memory requirement of the key and value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: The number of entries which are less recently used than the
+    ///   requested one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cache.nth_lru(1));
+    /// assert_eq!(None, cache.nth_lru(2));
+    /// ```
+    pub fn nth_lru(&self, n: usize) -> Option<(&K, &V)> {
+        if self.may_expire() {
+            return self.iter().nth(n);
+        }
+
+        self.nth_lru_ptr(n).map(|ptr|
             unsafe {
                 let entry = ptr.get_extended();
                 (entry.key(), entry.value())
             })
     }
 
+    /// Gets a reference to the `n`-th most-recently-used entry of this cache,
+    /// where the most-recently-used entry is at index 0. This returns both key
+    /// and value of the entry. If the cache contains no more than `n` entries,
+    /// `None` is returned.
+    ///
+    /// This method does not mark the value as most-recently-used. It takes
+    /// time linear in the distance of the entry from the closer end of the
+    /// usage history, i.e. at most half the number of entries. Expired
+    /// entries (see [LruCache::insert_with_ttl]) are skipped and not counted.
+    /// Once expiration times or a time-to-idle have been used, this requires
+    /// walking from the counted end, taking time linear in `n`.
+    ///
+    /// The memory requirement of the key and value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: The number of entries which are more recently used than the
+    ///   requested one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.nth_mru(1));
+    /// assert_eq!(None, cache.nth_mru(2));
+    /// ```
+    pub fn nth_mru(&self, n: usize) -> Option<(&K, &V)> {
+        if self.may_expire() {
+            return self.iter().nth_back(n);
+        }
+
+        let len = self.len();
+
+        if n >= len {
+            return None;
+        }
+
+        self.nth_lru(len - 1 - n)
+    }
+
+    /// Creates a cursor over the entries of this cache which initially points
+    /// to the least-recently-used entry. If the cache is empty, the cursor
+    /// points to no entry. See [CursorMut] for the available operations.
+    ///
+    /// Creating the cursor does not alter the usage history in any way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_lru();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cursor.current());
+    ///
+    /// cursor.move_next();
+    ///
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cursor.current());
+    /// ```
+    pub fn cursor_lru(&mut self) -> CursorMut<'_, K, V, S, W> {
+        let lru = self.seal.get().prev;
+        CursorMut::new(self, lru)
+    }
+
+    /// Creates a cursor over the entries of this cache which initially points
+    /// to the most-recently-used entry. If the cache is empty, the cursor
+    /// points to no entry. See [CursorMut] for the available operations.
+    ///
+    /// Creating the cursor does not alter the usage history in any way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let mut cursor = cache.cursor_mru();
+    ///
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cursor.current());
+    ///
+    /// cursor.move_prev();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cursor.current());
+    /// ```
+    pub fn cursor_mru(&mut self) -> CursorMut<'_, K, V, S, W> {
+        let mru = self.seal.get().next;
+        CursorMut::new(self, mru)
+    }
+
     fn new_capacity(&self, additional: usize)
             -> Result<usize, TryReserveError> {
         self.len().checked_add(additional)
@@ -990,6 +2072,11 @@ where
     /// size (see [LruCache::current_size]), the least-recently-used element
     /// will be repeatedly ejected until the limit is satisfied.
     ///
+    /// Pinned entries (see [LruCache::pin]) are never ejected. If the new
+    /// limit is below the [pinned size](LruCache::pinned_size), all unpinned
+    /// entries are ejected and the current size remains above the limit until
+    /// enough entries are unpinned and removed.
+    ///
     /// Note that reducing the memory limit to a small fraction of the previous
     /// maximum may lead to large amounts of unused capacity in the underlying
     /// data structure. If this is a problem, use [LruCache::shrink_to] or
@@ -1014,18 +2101,24 @@ where
     /// assert!(cache.max_size() < 1024);
     /// ```
     pub fn set_max_size(&mut self, max_size: usize) {
-        self.eject_to_target(max_size);
+        self.eject_to_target(max_size, RemovalCause::MaxSizeReduced);
         self.max_size = max_size;
     }
 
-    /// Sets the entry with the given key as most-recently-used, i.e. all other
-    /// entries currently contained in the cached will be dropped before this
-    /// one (unless others are touched/used afterwards). If there is no value
-    /// associated with the given key, this method is no-op.
+    /// Sets a new memory limit for this cache just like
+    /// [LruCache::set_max_size], but returns an iterator over the entries
+    /// ejected to meet the new limit, ordered from least- to
+    /// most-recently-used. This allows them to be moved elsewhere instead of
+    /// being dropped.
+    ///
+    /// Entries are ejected lazily as the iterator advances. If it is dropped
+    /// before being exhausted, the remaining entries which exceed the limit
+    /// are ejected and dropped. The new limit is in effect immediately.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the entry to touch.
+    /// * `max_size`: The new maximum number of bytes that the sum of the
+    /// memory estimates of all entries may occupy.
     ///
     /// # Example
     ///
@@ -1035,34 +2128,33 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    /// cache.touch(&"apple".to_owned());
+    /// let ejected = cache.resize(cache.current_size() - 1)
+    ///     .collect::<Vec<_>>();
     ///
-    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
-    ///     cache.remove_lru());
+    /// assert_eq!(vec![("apple".to_owned(), "sweet".to_owned())], ejected);
+    /// assert_eq!(1, cache.len());
     /// ```
-    pub fn touch<Q>(&mut self, key: &Q)
-    where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
-    {
-        if let Some(entry) = self.get_mut_from_table(key) {
-            let entry_ptr = EntryPtr::new(entry as *mut Entry<K, V>);
-            self.touch_ptr(entry_ptr);
-        }
+    pub fn resize(&mut self, max_size: usize) -> Eject<'_, K, V, S, W> {
+        self.max_size = max_size;
+        Eject::new(self, max_size, RemovalCause::MaxSizeReduced)
     }
 
-    /// Gets references to the key and value of the entry associated with the
-    /// given key. If there is no entry for that key, `None` is returned.
-    ///
-    /// This method also marks the entry as most-recently-used (see
-    /// [LruCache::touch]). If you do not want the usage history to be updated,
-    /// use [LruCache::peek_entry] instead.
+    /// Ejects least-recently-used entries from this cache until its current
+    /// size is at most the given target, without changing the memory limit.
+    /// This can be used to free memory proactively. Pinned entries (see
+    /// [LruCache::pin]) are never ejected, so the target may not be reached.
     ///
-    /// The memory requirement of the key and value may not be changed.
+    /// Returns an iterator over the ejected entries, ordered from least- to
+    /// most-recently-used, together with the number of bytes that are freed
+    /// by ejecting them. Entries are ejected lazily as the iterator advances.
+    /// If it is dropped before being exhausted, the remaining entries are
+    /// ejected and dropped, so the stated number of bytes is freed in any
+    /// case.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the entry to get.
+    /// * `target`: The number of bytes which the sum of the memory estimates
+    ///   of all entries should not exceed.
     ///
     /// # Example
     ///
@@ -1072,40 +2164,28 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let size_before = cache.current_size();
+    /// let (ejected, freed) = cache.evict_to(size_before - 1);
     ///
-    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
-    ///     cache.get_entry("apple"));
-    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
-    ///     cache.remove_lru());
+    /// assert_eq!(vec![("apple".to_owned(), "sweet".to_owned())],
+    ///     ejected.collect::<Vec<_>>());
+    /// assert_eq!(size_before - freed, cache.current_size());
+    /// assert_eq!(1024, cache.max_size());
     /// ```
-    pub fn get_entry<Q>(&mut self, key: &Q) -> Option<(&K, &V)>
-    where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
-    {
-        if let Some(entry) = self.get_mut_from_table(key) {
-            let entry_ptr = EntryPtr::new(entry as *mut Entry<K, V>);
-            self.touch_ptr(entry_ptr);
-            let entry = unsafe { entry_ptr.get_extended() };
-            Some(unsafe { (entry.key(), entry.value()) })
-        }
-        else {
-            None
-        }
+    pub fn evict_to(&mut self, target: usize)
+            -> (Eject<'_, K, V, S, W>, usize) {
+        let freed = self.ejected_size(target);
+        (Eject::new(self, target, RemovalCause::Ejected), freed)
     }
 
-    /// Gets a reference to the value associated with the given key. If there
-    /// is no value for that key, `None` is returned.
-    ///
-    /// This method also marks the value as most-recently-used (see
-    /// [LruCache::touch]). If you do not want the usage history to be updated,
-    /// use [LruCache::peek] instead.
-    ///
-    /// The memory requirement of the value may not be changed.
+    /// Ejects least-recently-used entries from this cache until at least the
+    /// given number of bytes are freed, or no unpinned entries remain. This is
+    /// equivalent to calling [LruCache::evict_to] with the current size
+    /// reduced by the given number of bytes as the target.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the value to get.
+    /// * `bytes`: The number of bytes to free at least.
     ///
     /// # Example
     ///
@@ -1115,30 +2195,25 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let (ejected, freed) = cache.evict_bytes(1);
     ///
-    /// assert_eq!(Some(&"sweet".to_owned()), cache.get("apple"));
-    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
-    ///     cache.remove_lru());
+    /// assert_eq!(1, ejected.count());
+    /// assert!(freed >= 1);
+    /// assert_eq!(1, cache.len());
     /// ```
-    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
-    where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
-    {
-        self.get_entry(key).map(|(_, v)| v)
+    pub fn evict_bytes(&mut self, bytes: usize)
+            -> (Eject<'_, K, V, S, W>, usize) {
+        self.evict_to(self.current_size.saturating_sub(bytes))
     }
 
-    /// Gets references to the key and value of the entry associated with the
-    /// given key. If there is no entry for that key, `None` is returned.
-    ///
-    /// This method does not mark the value as most-recently-used. If you want
-    /// the usage history to be updated, use [LruCache::get_entry] instead.
-    ///
-    /// The memory requirement of the key and value may not be changed.
+    /// Sets the entry with the given key as most-recently-used, i.e. all other
+    /// entries currently contained in the cached will be dropped before this
+    /// one (unless others are touched/used afterwards). If there is no value
+    /// associated with the given key, this method is no-op.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the entry to peek.
+    /// * `key`: The key of the entry to touch.
     ///
     /// # Example
     ///
@@ -1148,31 +2223,36 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.touch(&"apple".to_owned());
     ///
-    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
-    ///     cache.peek_entry("apple"));
-    /// assert_eq!(Some(("apple".to_owned(), "sweet".to_owned())),
+    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
     ///     cache.remove_lru());
     /// ```
-    pub fn peek_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    pub fn touch<Q>(&mut self, key: &Q)
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        self.get_from_table(key).map(|e| unsafe { (e.key(), e.value()) })
+        if let Some(entry_ptr) = self.get_live_ptr(key) {
+            self.touch_ptr(entry_ptr);
+        }
     }
 
-    /// Gets a reference to the value associated with the given key. If there
-    /// is no value for that key, `None` is returned.
+    /// Sets the entry with the given key as least-recently-used, i.e. it will
+    /// be dropped before all other entries currently contained in the cache
+    /// (unless others are demoted afterwards). This is the inverse of
+    /// [LruCache::touch] and useful for entries which are known not to be
+    /// used again soon, e.g. after serving a large one-off request. If there
+    /// is no value associated with the given key, this method is no-op.
     ///
-    /// This method does not mark the value as most-recently-used. If you want
-    /// the usage history to be updated, use [LruCache::get] instead.
-    ///
-    /// The memory requirement of the value may not be changed.
+    /// Demoting an entry does not count as accessing it, i.e. its idle time
+    /// (see [LruCache::set_time_to_idle]) is not reset. Instead, if the
+    /// previous least-recently-used entry has been idle for longer, the idle
+    /// time of the demoted entry is extended to match it. This keeps the
+    /// least-recently-used entry the one that has been idle the longest.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the value to peek.
+    /// * `key`: The key of the entry to demote.
     ///
     /// # Example
     ///
@@ -1182,25 +2262,42 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.demote(&"lemon".to_owned());
     ///
-    /// assert_eq!(Some(&"sweet".to_owned()), cache.peek("apple"));
-    /// assert_eq!(Some(("apple".to_owned(), "sweet".to_owned())),
+    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
     ///     cache.remove_lru());
     /// ```
-    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    pub fn demote<Q>(&mut self, key: &Q)
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        self.get_from_table(key).map(|e| unsafe { e.value() })
+        if let Some(entry_ptr) = self.get_live_ptr(key) {
+            self.demote_ptr(entry_ptr);
+        }
     }
 
-    /// Indicates whether this cache contains an entry associated with the
-    /// given key. If there is one, it is _not_ marked as most-recently-used.
+    /// Moves the entry with the given key in the usage history, such that it
+    /// is directly less recently used than the entry with the key `other`.
+    /// That is, it will be dropped right before that entry (unless either is
+    /// used afterwards). If there is no value associated with either key or
+    /// both keys are equal, this method is no-op.
+    ///
+    /// Moving an entry does not count as accessing it, i.e. its idle time
+    /// (see [LruCache::set_time_to_idle]) is not reset. Instead, it is
+    /// adjusted to lie between the idle times of its new neighbors in the
+    /// usage history, so less recently used entries are never idle for a
+    /// shorter time.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the value to search.
+    /// * `key`: The key of the entry to move.
+    /// * `other`: The key of the entry relative to which the entry with the
+    ///   key `key` is placed.
+    ///
+    /// # Returns
+    ///
+    /// `true`, if entries were found for both keys and the entry was moved,
+    /// and `false` otherwise.
     ///
     /// # Example
     ///
@@ -1209,25 +2306,66 @@ where
     ///
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("peach".to_owned(), "sweet".to_owned()).unwrap();
     ///
-    /// assert!(cache.contains("apple"));
-    /// assert!(!cache.contains("banana"));
+    /// assert!(cache.move_before("apple", "peach"));
+    /// assert_eq!(vec!["lemon", "apple", "peach"],
+    ///     cache.keys().collect::<Vec<_>>());
     /// ```
-    pub fn contains<Q>(&self, key: &Q) -> bool
+    pub fn move_before<Q>(&mut self, key: &Q, other: &Q) -> bool
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        let hash = make_hash::<Q, S>(&self.hash_builder, key);
-        self.table.find(hash, equivalent_key(key)).is_some()
+        let mut entry_ptr = match self.get_live_ptr(key) {
+            Some(entry_ptr) => entry_ptr,
+            None => return false
+        };
+
+        // Removing an expired entry with the key `other` does not move any
+        // other entry in the table, so `entry_ptr` remains valid.
+
+        let other_ptr = match self.get_live_ptr(other) {
+            Some(other_ptr) => other_ptr,
+            None => return false
+        };
+
+        if entry_ptr == other_ptr {
+            return false;
+        }
+
+        unsafe {
+            entry_ptr.unhinge();
+            entry_ptr.insert(other_ptr, other_ptr.get().next);
+            self.align_access(entry_ptr);
+        }
+
+        true
     }
 
-    /// Removes the entry associated with the given key from this cache. If the
-    /// cache does not contain the given key, `None` is returned.
+    /// Pins the entry with the given key, so it is exempt from being ejected
+    /// to make room for other entries, e.g. by [LruCache::insert] or
+    /// [LruCache::set_max_size]. It still counts towards the memory limit
+    /// and can still be removed explicitly, e.g. using [LruCache::remove].
+    /// Pinned entries also do not expire (see [LruCache::insert_with_ttl]).
+    /// The usage history is not altered.
+    ///
+    /// Entries keep a pin count, so an entry pinned multiple times must be
+    /// [unpinned](LruCache::unpin) equally often to become ejectable again.
+    /// If there is no value associated with the given key, this method is
+    /// no-op.
+    ///
+    /// Note that pinned entries are skipped whenever least-recently-used
+    /// entries are ejected, which takes time linear in the number of pinned
+    /// entries that are less recently used than the ejected ones.
     ///
     /// # Arguments
     ///
-    /// * `key`: The key of the value to remove.
+    /// * `key`: The key of the entry to pin.
+    ///
+    /// # Returns
+    ///
+    /// `true`, if an entry was found and pinned, and `false` otherwise.
     ///
     /// # Example
     ///
@@ -1236,25 +2374,45 @@ where
     ///
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
-    /// cache.remove("apple");
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.pin("apple");
+    /// cache.set_max_size(cache.current_size() - 1);
     ///
-    /// assert_eq!(0, cache.len());
+    /// assert!(cache.contains("apple"));
+    /// assert!(!cache.contains("lemon"));
     /// ```
-    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    pub fn pin<Q>(&mut self, key: &Q) -> bool
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        self.remove_from_table(key)
-            .map(|entry| unsafe { self.remove_metadata(entry) })
+        if let Some(entry_ptr) = self.get_live_ptr(key) {
+            let meta = unsafe { self.meta_mut(entry_ptr) };
+            meta.pins += 1;
+
+            if meta.pins == 1 {
+                self.pinned_size += entry_ptr.get().size;
+            }
+
+            true
+        }
+        else {
+            false
+        }
     }
 
-    /// Removes and returns the value associated with the given key from this
-    /// cache. If there is no such value, `None` is returned.
+    /// Reverts one call of [LruCache::pin] on the entry with the given key.
+    /// Once its pin count reaches zero, the entry can be ejected again. If
+    /// there is no value associated with the given key or it is not pinned,
+    /// this method is no-op.
     ///
-    /// # Argument
+    /// # Arguments
     ///
-    /// * `key`: The key of the value to remove.
+    /// * `key`: The key of the entry to unpin.
+    ///
+    /// # Returns
+    ///
+    /// `true`, if a pinned entry was found and unpinned once, and `false`
+    /// otherwise.
     ///
     /// # Example
     ///
@@ -1263,32 +2421,40 @@ where
     ///
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
-    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    ///
-    /// assert_eq!(Some("sour".to_owned()), cache.remove("lemon"));
-    /// assert_eq!(1, cache.len());
+    /// cache.pin("apple");
+    /// cache.pin("apple");
+    ///
+    /// assert!(cache.unpin("apple"));
+    /// assert!(cache.is_pinned("apple"));
+    /// assert!(cache.unpin("apple"));
+    /// assert!(!cache.is_pinned("apple"));
+    /// assert!(!cache.unpin("apple"));
     /// ```
-    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    pub fn unpin<Q>(&mut self, key: &Q) -> bool
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        self.remove_entry(key).map(|(_, v)| v)
+        match self.get_live_ptr(key) {
+            Some(entry_ptr) if unsafe { self.meta(entry_ptr).pins } > 0 => {
+                let meta = unsafe { self.meta_mut(entry_ptr) };
+                meta.pins -= 1;
+
+                if meta.pins == 0 {
+                    self.pinned_size -= entry_ptr.get().size;
+                }
+
+                true
+            },
+            _ => false
+        }
     }
 
-    /// Retains only the elements which satisfy the predicate. In other words,
-    /// removes all entries `(k, v)` such that `pred(&k, &v)` returns `false`.
-    /// The elements are visited ordered from least-recently-used to
-    /// most-recently-used.
-    ///
-    /// For all retained entries, i.e. those where `pred` returns `true`, the
-    /// memory requirement of the key and value may not be changed.
+    /// Indicates whether the entry associated with the given key is pinned
+    /// (see [LruCache::pin]). If there is no such entry, `false` is returned.
     ///
     /// # Arguments
     ///
-    /// * `pred`: A function which takes as input references to the key and
-    /// value of an entry and decides whether it should remain in the map
-    /// (`true`) or not (`false`).
+    /// * `key`: The key of the entry to check.
     ///
     /// # Example
     ///
@@ -1298,137 +2464,31 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
-    /// cache.insert("banana".to_owned(), "sweet".to_owned()).unwrap();
-    /// cache.retain(|_, v| v.as_str() == "sweet");
+    /// cache.pin("apple");
     ///
-    /// assert_eq!(2, cache.len());
-    /// assert!(cache.get("apple").is_some());
-    /// assert!(cache.get("lemon").is_none());
-    /// assert!(cache.get("banana").is_some());
+    /// assert!(cache.is_pinned("apple"));
+    /// assert!(!cache.is_pinned("lemon"));
     /// ```
-    pub fn retain<F>(&mut self, mut pred: F)
+    pub fn is_pinned<Q>(&self, key: &Q) -> bool
     where
-        F: FnMut(&K, &V) -> bool
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        let mut tail = self.seal.get().prev;
-
-        while tail != self.seal {
-            unsafe {
-                let entry = tail.get();
-                let key = entry.key();
-
-                if !pred(key, entry.value()) {
-                    self.remove_entry(key);
-                }
-
-                tail = entry.prev;
-            }
-        }
-    }
-}
-
-struct EntryTooLarge<K, V> {
-    key: K,
-    value: V,
-    entry_size: usize,
-    max_size: usize
-}
-
-impl<K, V> From<EntryTooLarge<K, V>> for InsertError<K, V> {
-    fn from(data: EntryTooLarge<K, V>) -> InsertError<K, V> {
-        InsertError::EntryTooLarge {
-            key: data.key,
-            value: data.value,
-            entry_size: data.entry_size,
-            max_size: data.max_size
-        }
-    }
-}
-
-impl<K, V> From<EntryTooLarge<K, V>> for TryInsertError<K, V> {
-    fn from(data: EntryTooLarge<K, V>) -> TryInsertError<K, V> {
-        TryInsertError::EntryTooLarge {
-            key: data.key,
-            value: data.value,
-            entry_size: data.entry_size,
-            max_size: data.max_size
-        }
-    }
-}
-
-impl<K, V, S> LruCache<K, V, S>
-where
-    K: Eq + Hash + MemSize,
-    V: MemSize,
-    S: BuildHasher
-{
-    fn prepare_insert(&mut self, key: K, value: V)
-            -> Result<UnhingedEntry<K, V>, EntryTooLarge<K, V>> {
-        let entry = UnhingedEntry::new(key, value);
-        let entry_size = entry.size();
-
-        if entry_size > self.max_size {
-            let (key, value) = entry.into_key_value();
-
-            Err(EntryTooLarge {
-                key,
-                value,
-                entry_size,
-                max_size: self.max_size
-            })
-        }
-        else {
-            Ok(entry)
-        }
-    }
-
-    fn insert_unchecked(&mut self, entry: UnhingedEntry<K, V>, hash: u64) {
-        let size = entry.size();
-        let mut entry = Entry::new(entry, self.seal, self.seal.get().next);
-
-        loop {
-            match self.insert_into_table_with_hash(hash, entry) {
-                Ok(entry_ptr) => {
-                    self.current_size += size;
-                    self.set_head(entry_ptr);
-                    return;
-                },
-                Err(returned_entry) => {
-                    entry = returned_entry;
-                    self.reallocate((self.table.capacity() * 2).max(1));
-                    
-                    // The seal pointer stays constant through reallocation, so
-                    // only entry.next has to be set.
-
-                    entry.next = self.seal.get().next;
-                }
-            }
-        }
+        self.get_ptr_from_table(key)
+            .is_some_and(|entry_ptr| unsafe { self.meta(entry_ptr).pins } > 0)
     }
 
-    /// Inserts a new entry into this cache. This is initially the
-    /// most-recently-used entry. If there was an entry with the given key
-    /// before, it is removed and its value returned. Otherwise, `None` is
-    /// returned. If inserting this entry would violate the memory limit,
-    /// the least-recently-used values are ejected from the cache until it
-    /// fits.
-    ///
-    /// If you want to know before calling this method whether elements would
-    /// be ejected, you can use [entry_size] to obtain the memory usage that
-    /// would be assigned to the created entry and check using
-    /// [LruCache::current_size] and [LruCache::max_size] whether it fits.
+    /// Gets references to the key and value of the entry associated with the
+    /// given key. If there is no entry for that key, `None` is returned.
     ///
-    /// # Arguments
+    /// This method also marks the entry as most-recently-used (see
+    /// [LruCache::touch]). If you do not want the usage history to be updated,
+    /// use [LruCache::peek_entry] instead.
     ///
-    /// * `key`: The key by which the inserted entry will be identified.
-    /// * `value`: The value to store in the inserted entry.
+    /// The memory requirement of the key and value may not be changed.
     ///
-    /// # Errors
+    /// # Arguments
     ///
-    /// Raises an [InsertError::EntryTooLarge] if the entry alone would already
-    /// be too large to fit inside the cache's size limit. That is, even if all
-    /// other entries were ejected, it still would not be able to be inserted.
-    /// If this occurs, the entry was not inserted.
+    /// * `key`: The key of the entry to get.
     ///
     /// # Example
     ///
@@ -1439,47 +2499,40 @@ where
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
     ///
-    /// assert_eq!(2, cache.len());
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.get_entry("apple"));
+    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
+    ///     cache.remove_lru());
     /// ```
-    pub fn insert(&mut self, key: K, value: V)
-            -> Result<Option<V>, InsertError<K, V>> {
-        let entry = self.prepare_insert(key, value)?;
-
-        // Deduplicate keys, make space
-
-        let key = entry.key();
-        let hash = make_insert_hash::<K, S>(&self.hash_builder, key);
-        let result = self.table.remove_entry(hash, equivalent_key(key))
-            .map(|e| unsafe { self.remove_metadata(e).1 });
-        self.eject_to_target(self.max_size - entry.size());
-
-        // Insert entry at head of list
-
-        self.insert_unchecked(entry, hash);
-        Ok(result)
+    pub fn get_entry<Q>(&mut self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        if let Some(entry_ptr) = self.get_live_ptr(key) {
+            self.touch_ptr(entry_ptr);
+            let entry = unsafe { entry_ptr.get_extended() };
+            Some(unsafe { (entry.key(), entry.value()) })
+        }
+        else {
+            None
+        }
     }
 
-    /// Tries to insert a new entry into this cache. This is initially the
-    /// most-recently-used entry. If there was an entry with the given key
-    /// before or it does not fit within the memory requirement, an appropriate
-    /// error is raised (see below).
+    /// Gets a reference to the value associated with the given key. If there
+    /// is no value for that key, `None` is returned.
     ///
-    /// # Arguments
+    /// This method also marks the value as most-recently-used (see
+    /// [LruCache::touch]). If you do not want the usage history to be updated,
+    /// use [LruCache::peek] instead.
     ///
-    /// * `key`: The key by which the inserted entry will be identified.
-    /// * `value`: The value to store in the inserted entry.
+    /// If the entry is expired (see [LruCache::insert_with_ttl]), it is
+    /// removed and `None` is returned.
     ///
-    /// # Errors
+    /// The memory requirement of the value may not be changed.
     ///
-    /// * Raises an [TryInsertError::EntryTooLarge] if the entry alone would
-    /// already be too large to fit inside the cache's size limit.
-    /// * Otherwise, raises a [TryInsertError::WouldEjectLru] if the entry does
-    /// not fit within the remaining free memory of the cache, i.e. the
-    /// difference between [LruCache::max_size] and [LruCache::current_size].
-    /// * Otherwise, raises an [TryInsertError::OccupiedEntry] if there was
-    /// already an entry with the given key.
+    /// # Arguments
     ///
-    /// If any error was raised, the entry was not inserted into the cache.
+    /// * `key`: The key of the value to get.
     ///
     /// # Example
     ///
@@ -1487,73 +2540,36 @@ where
     /// use lru_mem::LruCache;
     ///
     /// let mut cache = LruCache::new(1024);
-    /// let result = cache.try_insert("apple".to_owned(), "sweet".to_owned());
-    /// assert!(result.is_ok());
-    /// let result = cache.try_insert("apple".to_owned(), "sour".to_owned());
-    /// assert!(!result.is_ok());
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some(&"sweet".to_owned()), cache.get("apple"));
+    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
+    ///     cache.remove_lru());
     /// ```
-    pub fn try_insert(&mut self, key: K, value: V)
-            -> Result<(), TryInsertError<K, V>> {
-        let entry = self.prepare_insert(key, value)?;
-
-        // Check that the entry fits
-
-        let free_memory = self.max_size - self.current_size;
-        let entry_size = entry.size();
-
-        if entry_size > free_memory {
-            let (key, value) = entry.into_key_value();
-
-            return Err(TryInsertError::WouldEjectLru {
-                key,
-                value,
-                entry_size,
-                free_memory
-            })
-        }
-
-        // Check that the entry is not occupied
-
-        let key = entry.key();
-        let hash = make_insert_hash::<K, S>(&self.hash_builder, key);
-
-        if self.table.find(hash, equivalent_key(key)).is_some() {
-            let (key, value) = entry.into_key_value();
-
-            return Err(TryInsertError::OccupiedEntry {
-                key,
-                value
-            })
-        }
-
-        self.insert_unchecked(entry, hash);
-        Ok(())
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        self.get_entry(key).map(|(_, v)| v)
     }
 
-    /// Applies a mutating function to the value associated with the given key.
-    /// The result of that function is returned. If there is no value for the
-    /// given key, `None` is returned, and the operation is never called.
-    /// Otherwise, the entry is marked as most-recently-used by this method.
+    /// Gets references to the values associated with each of the given keys.
+    /// For each key without a value, `None` is returned at its position.
     ///
-    /// Note that the operation may also change the size of the value. After it
-    /// terminates, the internal sizes are updated and, if necessary,
-    /// least-recently-used entries are ejected to restore the memory
-    /// requirement. If the operation increases the size beyond the limit of
-    /// this cache, an error is raised (see below).
+    /// This method also marks all found values as most-recently-used, in the
+    /// order of the given keys, as if [LruCache::get] was called for each of
+    /// them. If you do not want the usage history to be updated, use
+    /// [LruCache::peek] instead.
     ///
-    /// # Arguments
+    /// Expired entries (see [LruCache::insert_with_ttl]) are removed and
+    /// treated as absent.
     ///
-    /// * `key`: The key of the value to mutate.
-    /// * `op`: An operation that takes as input a mutable reference to the
-    /// value, mutates it, and returns the desired result. This is forwarded by
-    /// this method to the caller.
+    /// The memory requirement of the values may not be changed.
     ///
-    /// # Errors
+    /// # Arguments
     ///
-    /// Raises an [MutateError::EntryTooLarge] if the operation expanded the
-    /// value so much that the entry no longer fit inside the memory limit of
-    /// the cache. If that is the case, the entry is removed and its parts
-    /// returned in the error data.
+    /// * `keys`: The keys of the values to get. These may contain duplicates.
     ///
     /// # Example
     ///
@@ -1563,794 +2579,4540 @@ where
     /// let mut cache = LruCache::new(1024);
     /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
     /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("peach".to_owned(), "sweet".to_owned()).unwrap();
     ///
-    /// assert_eq!(Ok(Some(())),
-    ///     cache.mutate("apple", |s| s.push_str(" and sour")));
-    /// assert_eq!(Some(&"sweet and sour".to_owned()), cache.peek("apple"));
+    /// assert_eq!([Some(&"sweet".to_owned()), None, Some(&"sour".to_owned())],
+    ///     cache.get_many(["apple", "grape", "lemon"]));
+    /// assert_eq!(Some(("peach".to_owned(), "sweet".to_owned())),
+    ///     cache.remove_lru());
     /// ```
-    pub fn mutate<Q, R, F>(&mut self, key: &Q, op: F)
-        -> Result<Option<R>, MutateError<K, V>>
+    pub fn get_many<Q, const N: usize>(&mut self, keys: [&Q; N])
+        -> [Option<&V>; N]
     where
-        K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized,
-        F: FnOnce(&mut V) -> R
+        Q: Hash + Equivalent<K> + ?Sized
     {
-        let max_size = self.max_size;
+        // Removing expired entries does not move any other entry in the
+        // table, so pointers obtained earlier remain valid.
 
-        if let Some(entry) = self.get_mut_from_table(key) {
-            let new_value_size;
-            let result;
-            let old_value_size;
+        let entry_ptrs = keys.map(|key| {
+            let entry_ptr = self.get_live_ptr(key)?;
+            self.touch_ptr(entry_ptr);
+            Some(entry_ptr)
+        });
 
-            unsafe {
-                old_value_size = entry.value().mem_size();
-                result = op(entry.value_mut());
-                new_value_size = entry.value().mem_size();
-            }
+        entry_ptrs.map(|entry_ptr|
+            entry_ptr.map(|entry_ptr| unsafe {
+                entry_ptr.get_extended().value()
+            }))
+    }
 
-            if new_value_size > old_value_size {
-                // The operation was expanding; we must ensure it still fits.
+    /// Gets references to the key and value of the entry associated with the
+    /// given key. If there is no entry for that key, `None` is returned.
+    ///
+    /// This method does not mark the value as most-recently-used. If you want
+    /// the usage history to be updated, use [LruCache::get_entry] instead.
+    ///
+    /// Expired entries (see [LruCache::insert_with_ttl]) are treated as absent.
+    /// Since this method does not mutate the cache, their memory is only
+    /// reclaimed once they are accessed mutably or [LruCache::purge_expired]
+    /// is called.
+    ///
+    /// The memory requirement of the key and value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry to peek.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.peek_entry("apple"));
+    /// assert_eq!(Some(("apple".to_owned(), "sweet".to_owned())),
+    ///     cache.remove_lru());
+    /// ```
+    pub fn peek_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        self.get_from_table(key).map(|e| unsafe { (e.key(), e.value()) })
+    }
+
+    /// Gets a reference to the value associated with the given key. If there
+    /// is no value for that key, `None` is returned.
+    ///
+    /// This method does not mark the value as most-recently-used. If you want
+    /// the usage history to be updated, use [LruCache::get] instead.
+    ///
+    /// Expired entries (see [LruCache::insert_with_ttl]) are treated as absent.
+    /// Since this method does not mutate the cache, their memory is only
+    /// reclaimed once they are accessed mutably or [LruCache::purge_expired]
+    /// is called.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to peek.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some(&"sweet".to_owned()), cache.peek("apple"));
+    /// assert_eq!(Some(("apple".to_owned(), "sweet".to_owned())),
+    ///     cache.remove_lru());
+    /// ```
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        self.get_from_table(key).map(|e| unsafe { e.value() })
+    }
+
+    /// Determines how close the entry associated with the given key is to
+    /// being ejected. This returns the number of entries which are more
+    /// recently used than it and the sum of their memory requirements in
+    /// bytes, i.e. `(0, 0)` for the most-recently-used entry. If there is no
+    /// value for the given key, `None` is returned. The usage history is not
+    /// altered.
+    ///
+    /// Note that pinned entries (see [LruCache::pin]) are counted as well,
+    /// even though they are not ejected. Expired entries (see
+    /// [LruCache::insert_with_ttl]) are treated as absent and not counted.
+    ///
+    /// This method takes time linear in the number of entries which are more
+    /// recently used than the one with the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry whose rank to determine.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{entry_size, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let lemon_size = entry_size(&"lemon".to_owned(), &"sour".to_owned());
+    ///
+    /// assert_eq!(Some((1, lemon_size)), cache.rank_of("apple"));
+    /// assert_eq!(Some((0, 0)), cache.rank_of("lemon"));
+    /// assert_eq!(None, cache.rank_of("peach"));
+    /// ```
+    pub fn rank_of<Q>(&self, key: &Q) -> Option<(usize, usize)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let mut entries = 0;
+        let mut bytes = 0;
+        let mut next = self.get_from_table(key)?.prev;
+        let expiry_check = self.expiry_check();
+
+        while next != self.seal {
+            let entry = next.get();
+            let expired = expiry_check.as_ref()
+                .is_some_and(|check| unsafe { check.is_expired(next) });
+
+            if !expired {
+                entries += 1;
+                bytes += entry.size;
+            }
+
+            next = entry.prev;
+        }
+
+        Some((entries, bytes))
+    }
+
+    /// Creates an iterator over references to the entries of this cache
+    /// starting at the entry associated with the given key and proceeding
+    /// towards the most-recently-used one. That is, it yields the entry with
+    /// the given key and all entries which are more recently used, ordered
+    /// from least- to most-recently-used. If there is no value for the given
+    /// key, `None` is returned. The usage history is not altered.
+    ///
+    /// The entry is located in constant time.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry at which to start iterating.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple", "red").unwrap();
+    /// cache.insert("banana", "yellow").unwrap();
+    /// cache.insert("lemon", "yellow").unwrap();
+    ///
+    /// let keys = cache.iter_from("banana").unwrap()
+    ///     .map(|(k, _)| *k)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(vec!["banana", "lemon"], keys);
+    /// assert!(cache.iter_from("peach").is_none());
+    /// ```
+    pub fn iter_from<Q>(&self, key: &Q) -> Option<Iter<'_, K, V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let entry_ptr = self.get_ptr_from_table(key)?;
+
+        unsafe {
+            Some(Iter::new_range(entry_ptr, self.seal.get().next,
+                self.expiry_check()))
+        }
+    }
+
+    /// Creates an iterator over references to the entries of this cache
+    /// ending at the entry associated with the given key. That is, it yields
+    /// all entries which are less recently used than the one with the given
+    /// key and that entry itself, ordered from least- to most-recently-used.
+    /// Iterating from the back, e.g. using [Iterator::rev], starts at the
+    /// entry with the given key and proceeds towards the least-recently-used
+    /// one. If there is no value for the given key, `None` is returned. The
+    /// usage history is not altered.
+    ///
+    /// The entry is located in constant time.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry at which iteration from the back starts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple", "red").unwrap();
+    /// cache.insert("banana", "yellow").unwrap();
+    /// cache.insert("lemon", "yellow").unwrap();
+    ///
+    /// let keys = cache.iter_from_back("banana").unwrap()
+    ///     .rev()
+    ///     .map(|(k, _)| *k)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(vec!["banana", "apple"], keys);
+    /// assert!(cache.iter_from_back("peach").is_none());
+    /// ```
+    pub fn iter_from_back<Q>(&self, key: &Q) -> Option<Iter<'_, K, V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let entry_ptr = self.get_ptr_from_table(key)?;
+
+        unsafe {
+            Some(Iter::new_range(self.seal.get().prev, entry_ptr,
+                self.expiry_check()))
+        }
+    }
+
+    /// Indicates whether this cache contains an entry associated with the
+    /// given key. If there is one, it is _not_ marked as most-recently-used.
+    ///
+    /// Expired entries (see [LruCache::insert_with_ttl]) are treated as absent.
+    /// Since this method does not mutate the cache, their memory is only
+    /// reclaimed once they are accessed mutably or [LruCache::purge_expired]
+    /// is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert!(cache.contains("apple"));
+    /// assert!(!cache.contains("banana"));
+    /// ```
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        self.get_from_table(key).is_some()
+    }
+
+    /// Removes the entry associated with the given key from this cache. If the
+    /// cache does not contain the given key, `None` is returned. Expired
+    /// entries (see [LruCache::insert_with_ttl]) are also removed, but treated
+    /// as absent.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to remove.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.remove("apple");
+    ///
+    /// assert_eq!(0, cache.len());
+    /// ```
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let hash = make_hash::<Q, S>(&self.hash_builder, key);
+        let bucket = self.table.find(hash, equivalent_key(key))?;
+
+        unsafe {
+            if self.is_bucket_expired(&bucket) {
+                self.remove_bucket(bucket, RemovalCause::Expired);
+                None
+            }
+            else {
+                Some(self.take_bucket(bucket))
+            }
+        }
+    }
+
+    /// Removes and returns the value associated with the given key from this
+    /// cache. If there is no such value, `None` is returned.
+    ///
+    /// # Argument
+    ///
+    /// * `key`: The key of the value to remove.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some("sour".to_owned()), cache.remove("lemon"));
+    /// assert_eq!(1, cache.len());
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Computes the hash of the given key using the hasher of this cache. The
+    /// result can be passed to the builders returned by [LruCache::raw_entry]
+    /// and [LruCache::raw_entry_mut] to avoid hashing the same key again for
+    /// every operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key to hash.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// assert_eq!(hash, cache.hash_key(&"apple".to_owned()));
+    /// assert!(cache.raw_entry().from_key_hashed_nocheck(hash, "apple")
+    ///     .is_some());
+    /// ```
+    pub fn hash_key<Q>(&self, key: &Q) -> u64
+    where
+        Q: Hash + ?Sized
+    {
+        make_hash::<Q, S>(&self.hash_builder, key)
+    }
+
+    /// Creates a builder for looking up entries of this cache by a
+    /// precomputed hash (see [LruCache::hash_key]) without altering the usage
+    /// history. See [RawEntryBuilder] for the available lookups.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.raw_entry().from_key_hashed_nocheck(hash, "apple"));
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.peek_lru());
+    /// ```
+    pub fn raw_entry(&self) -> RawEntryBuilder<'_, K, V, S, W> {
+        RawEntryBuilder::new(self)
+    }
+
+    /// Creates a builder for getting entries of this cache by a precomputed
+    /// hash (see [LruCache::hash_key]) for in-place manipulation. As with
+    /// [LruCache::entry], a found entry is marked as most-recently-used. See
+    /// [RawEntryBuilderMut] for the available lookups.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// for count in 1..=3 {
+    ///     match cache.raw_entry_mut().from_key_hashed_nocheck(hash, "apple") {
+    ///         RawEntryMut::Occupied(entry) => {
+    ///             entry.mutate(|v| *v += 1).unwrap();
+    ///         },
+    ///         RawEntryMut::Vacant(entry) => {
+    ///             entry.insert("apple".to_owned(), 1).unwrap();
+    ///         }
+    ///     }
+    ///
+    ///     assert_eq!(Some(&count), cache.peek("apple"));
+    /// }
+    /// ```
+    pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, S, W> {
+        RawEntryBuilderMut::new(self)
+    }
+
+    /// Creates an iterator which removes all entries satisfying the given
+    /// predicate from this cache and yields them as key-value-pairs. The
+    /// entries are visited ordered from least-recently-used to
+    /// most-recently-used. Entries for which the predicate returns `false`
+    /// remain in the cache and their usage history is not altered.
+    ///
+    /// Entries are removed lazily as the iterator advances. If it is dropped
+    /// before being exhausted, the remaining entries stay in the cache, even
+    /// if they satisfy the predicate. Use [LruCache::retain] if the removed
+    /// entries are not required.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred`: A function which takes as input references to the key and
+    ///   value of an entry and decides whether it should be removed and
+    ///   yielded (`true`) or not (`false`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("banana".to_owned(), "sweet".to_owned()).unwrap();
+    /// let sweet = cache.extract_if(|_, v| v.as_str() == "sweet")
+    ///     .map(|(k, _)| k)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(vec!["apple".to_owned(), "banana".to_owned()], sweet);
+    /// assert_eq!(1, cache.len());
+    /// ```
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, S, F, W>
+    where
+        F: FnMut(&K, &V) -> bool
+    {
+        ExtractIf::new(self, pred)
+    }
+
+    /// Retains only the elements which satisfy the predicate. In other words,
+    /// removes all entries `(k, v)` such that `pred(&k, &v)` returns `false`.
+    /// The elements are visited ordered from least-recently-used to
+    /// most-recently-used.
+    ///
+    /// For all retained entries, i.e. those where `pred` returns `true`, the
+    /// memory requirement of the key and value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred`: A function which takes as input references to the key and
+    /// value of an entry and decides whether it should remain in the map
+    /// (`true`) or not (`false`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("banana".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.retain(|_, v| v.as_str() == "sweet");
+    ///
+    /// assert_eq!(2, cache.len());
+    /// assert!(cache.get("apple").is_some());
+    /// assert!(cache.get("lemon").is_none());
+    /// assert!(cache.get("banana").is_some());
+    /// ```
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&K, &V) -> bool
+    {
+        let mut tail = self.seal.get().prev;
+
+        while tail != self.seal {
+            unsafe {
+                let entry = tail.get();
+                let prev = entry.prev;
+
+                if !pred(entry.key(), entry.value()) {
+                    let (key, value) = self.remove_ptr(tail);
+                    self.notify_removal(key, value, RemovalCause::Filtered);
+                }
+
+                tail = prev;
+            }
+        }
+    }
+
+    /// Removes all expired entries (see [LruCache::insert_with_ttl]) from this
+    /// cache, reclaiming their memory. This includes idle entries (see
+    /// [LruCache::set_time_to_idle]). Since expired entries are otherwise
+    /// only removed once they are accessed, this can be called periodically
+    /// to free memory occupied by entries that are never accessed again. This
+    /// runs in O(n), where n is the number of entries.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert_with_ttl("apple".to_owned(), "sweet".to_owned(),
+    ///     Duration::ZERO).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(2, cache.len());
+    ///
+    /// cache.purge_expired();
+    ///
+    /// assert_eq!(1, cache.len());
+    /// assert!(cache.contains("lemon"));
+    /// ```
+    pub fn purge_expired(&mut self) {
+        let now = self.now();
+        let mut tail = self.seal.get().prev;
+
+        while tail != self.seal {
+            unsafe {
+                let prev = tail.get().prev;
+                let expired =
+                    self.meta(tail).is_expired_at(self.time_to_idle, now);
+
+                if expired {
+                    let (key, value) = self.remove_ptr(tail);
+                    self.notify_removal(key, value, RemovalCause::Expired);
+                }
+
+                tail = prev;
+            }
+        }
+    }
+
+    /// Removes all entries that have not been accessed for longer than the
+    /// time-to-idle of this cache (see [LruCache::set_time_to_idle]),
+    /// reclaiming their memory. Starting at the least-recently-used entry,
+    /// entries are removed until one is found that is not idle, so this runs
+    /// in O(m), where m is the number of removed entries. Pinned entries are
+    /// skipped. If no time-to-idle is set, this method does nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.set_time_to_idle(Some(Duration::ZERO));
+    /// cache.remove_idle();
+    ///
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn remove_idle(&mut self) {
+        if self.time_to_idle.is_none() {
+            return;
+        }
+
+        let now = self.now();
+        let mut tail = self.seal.get().prev;
+
+        while tail != self.seal {
+            unsafe {
+                let prev = tail.get().prev;
+                let meta = self.meta(tail);
+
+                if meta.pins == 0 {
+                    let idle = meta.idle_deadline(self.time_to_idle)
+                        .is_some_and(|idle_deadline| idle_deadline <= now);
+
+                    if !idle {
+                        break;
+                    }
+
+                    let (key, value) = self.remove_ptr(tail);
+                    self.notify_removal(key, value, RemovalCause::Expired);
+                }
+
+                tail = prev;
+            }
+        }
+    }
+
+    /// Merges all entries of the given cache into this one. In contrast to
+    /// inserting them one by one, the usage histories of both caches are
+    /// interleaved, i.e. each entry is placed according to when it was last
+    /// used relative to the entries of this cache. Afterwards,
+    /// least-recently-used entries are ejected until the memory requirement
+    /// of this cache is restored.
+    ///
+    /// When it was last used is determined by a logical access stamp, which
+    /// each cache increments on every access of an entry. Since caches count
+    /// their accesses independently, an entry last used at the n-th access of
+    /// the other cache is placed next to the entries last used around the
+    /// n-th access of this cache. Afterwards, this cache continues counting
+    /// from the greater count of both caches. Entries demoted using
+    /// [LruCache::demote] or inserted using [LruCache::insert_cold] count as
+    /// being used when the previous least-recently-used entry was.
+    ///
+    /// If both caches contain an entry with the same key, only one of them is
+    /// kept, as decided by the given [ConflictPolicy]. The discarded entry is
+    /// reported to the removal listener of the cache it was contained in with
+    /// [RemovalCause::Replaced]. Expired entries (see
+    /// [LruCache::insert_with_ttl]) are discarded in any case and reported
+    /// with [RemovalCause::Expired]. Entries retain their expiration times and
+    /// pin counts. If a time-to-idle is set for this cache (see
+    /// [LruCache::set_time_to_idle]), idle times are only ever extended, such
+    /// that less recently used entries are not idle for a shorter time. Keys
+    /// and values are moved, not cloned.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The cache whose entries to merge into this one.
+    /// * `conflict_policy`: The rule deciding which entry to keep for keys
+    ///   contained in both caches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{ConflictPolicy, LruCache};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// let mut other = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("peach".to_owned(), "sweet".to_owned()).unwrap();
+    /// other.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// other.insert("apple".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.merge(other, ConflictPolicy::KeepMoreRecent);
+    ///
+    /// assert_eq!(vec!["lemon", "peach", "apple"],
+    ///     cache.keys().collect::<Vec<_>>());
+    /// assert_eq!(Some(&"sour".to_owned()), cache.peek("apple"));
+    /// ```
+    pub fn merge(&mut self, mut other: LruCache<K, V, S, W>,
+            conflict_policy: ConflictPolicy) {
+
+        // Move all entries out of the other cache before running any user
+        // code, so it is left in a consistent, empty state.
+
+        let mut incoming = Vec::with_capacity(other.len());
+        let mut tail = other.seal.get().prev;
+
+        while tail != other.seal {
+            let (entry, meta) = unsafe { (tail.read(), *other.meta(tail)) };
+            tail = entry.prev;
+            incoming.push((entry, meta));
+        }
+
+        other.seal.get_mut().next = other.seal;
+        other.seal.get_mut().prev = other.seal;
+        other.current_size = 0;
+        other.pinned_size = 0;
+        other.table.clear_no_drop();
+
+        self.reserve(incoming.len());
+
+        // Both lists are ordered by access stamps, so each incoming entry is
+        // inserted directly less recently used than the first entry of this
+        // cache with a greater stamp. That position only moves towards the
+        // most-recently-used end.
+
+        let mut position = self.seal.get().prev;
+
+        for (entry, meta) in incoming {
+            unsafe {
+                if other.is_expired(&meta) {
+                    let (key, value) = entry.into_key_value();
+                    other.notify_removal(key, value, RemovalCause::Expired);
+                    continue;
+                }
+
+                let hash =
+                    make_insert_hash::<K, S>(&self.hash_builder, entry.key());
+
+                if let Some(bucket) =
+                        self.table.find(hash, equivalent_key(entry.key())) {
+                    let existing_meta = self.bucket_meta(&bucket);
+                    let expired = self.is_expired(existing_meta);
+                    let keep_existing = !expired && match conflict_policy {
+                        ConflictPolicy::KeepExisting => true,
+                        ConflictPolicy::KeepIncoming => false,
+                        ConflictPolicy::KeepMoreRecent =>
+                            existing_meta.access_stamp >= meta.access_stamp
+                    };
+
+                    if keep_existing {
+                        let (key, value) = entry.into_key_value();
+                        other.notify_removal(key, value,
+                            RemovalCause::Replaced);
+                        continue;
+                    }
+
+                    if EntryPtr::new(bucket.as_ptr()) == position {
+                        position = position.get().prev;
+                    }
+
+                    let cause = if expired {
+                        RemovalCause::Expired
+                    }
+                    else {
+                        RemovalCause::Replaced
+                    };
+
+                    self.remove_bucket(bucket, cause);
+                }
+
+                while position != self.seal &&
+                        self.meta(position).access_stamp <= meta.access_stamp {
+                    position = position.get().prev;
+                }
+
+                let size = entry.size;
+
+                if meta.pins > 0 {
+                    self.pinned_size += size;
+                }
+
+                self.current_size += size;
+
+                let mut entry_ptr = self.insert_into_table_with_hash(hash,
+                    entry, meta).unwrap_unchecked();
+                entry_ptr.insert(position, position.get().next);
+            }
+        }
+
+        self.access_counter = self.access_counter.max(other.access_counter);
+        self.expiration_used |= other.expiration_used;
+
+        // Incoming entries carry the idle times of the other cache, if any.
+        // Backdating them where necessary keeps the least-recently-used entry
+        // the one that has been idle the longest.
+
+        if self.time_to_idle.is_some() {
+            let mut newer_access = self.now();
+            let mut entry_ptr = self.seal.get().next;
+
+            while entry_ptr != self.seal {
+                unsafe {
+                    let meta = self.meta_mut(entry_ptr);
+                    let last_access = meta.last_access.map_or(newer_access,
+                        |access| access.min(newer_access));
+                    meta.last_access = Some(last_access);
+                    newer_access = last_access;
+                    entry_ptr = entry_ptr.get().next;
+                }
+            }
+        }
+
+        self.eject_to_target(self.max_size, RemovalCause::Ejected);
+    }
+}
+
+impl<K, V, S, W> LruCache<K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+    W: Clone
+{
+
+    /// Moves the `n` least-recently-used entries into a new cache, preserving
+    /// their metadata and relative order. The new cache has the same
+    /// configuration as this one, except for the removal listener.
+    fn split_off_lru_entries(&mut self, n: usize) -> LruCache<K, V, S, W> {
+        let mut other = LruCache::with_table_hasher_and_weigher(
+            self.max_size, RawTable::with_capacity(n),
+            self.hash_builder.clone(), self.weigher.clone());
+        other.default_ttl = self.default_ttl;
+        other.time_to_idle = self.time_to_idle;
+        other.clock = self.clock.clone();
+        other.access_counter = self.access_counter;
+        other.expiration_used = self.expiration_used;
+
+        for _ in 0..n {
+            let entry_ptr = match self.lru_ptr() {
+                Some(entry_ptr) => entry_ptr,
+                None => break
+            };
+
+            unsafe {
+                entry_ptr.unhinge();
+                let meta = *self.meta(entry_ptr);
+                let (entry, _) = self.table.remove(self.bucket_of(entry_ptr));
+
+                if meta.pins > 0 {
+                    self.pinned_size -= entry.size;
+                    other.pinned_size += entry.size;
+                }
+
+                self.current_size -= entry.size;
+                other.current_size += entry.size;
+
+                // Entries are moved from least- to most-recently-used, so the
+                // order is preserved by inserting each one at the head.
+
+                other.insert_untracked(entry, meta);
+            }
+        }
+
+        other
+    }
+
+    /// Splits off the least-recently-used entries of this cache into a new
+    /// cache, such that the sum of their memory requirements is at least the
+    /// given number of bytes, or all entries are split off if that is not
+    /// possible. The split off entries keep their relative order in the usage
+    /// history, as well as their expiration times and pin counts. Keys and
+    /// values are moved, not cloned.
+    ///
+    /// The new cache uses a clone of the hasher of this cache and has the same
+    /// maximum size, default time-to-live, time-to-idle, and clock. The
+    /// removal listener is not transferred. Removing entries from this cache by
+    /// splitting them off is not reported to the removal listener.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The minimum number of bytes that the entries split off
+    ///   should occupy together.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("banana".to_owned(), "sweet".to_owned()).unwrap();
+    /// let other = cache.split_off_lru(1);
+    ///
+    /// assert_eq!(vec![&"apple".to_owned()], other.keys().collect::<Vec<_>>());
+    /// assert_eq!(2, cache.len());
+    /// ```
+    pub fn split_off_lru(&mut self, bytes: usize) -> LruCache<K, V, S, W> {
+        let mut n = 0;
+        let mut size = 0;
+        let mut tail = self.seal.get().prev;
+
+        while size < bytes && tail != self.seal {
+            let entry = tail.get();
+            size += entry.size;
+            n += 1;
+            tail = entry.prev;
+        }
+
+        self.split_off_lru_entries(n)
+    }
+
+    /// Splits off the given number of least-recently-used entries of this
+    /// cache into a new cache, or all entries if the cache contains fewer. The
+    /// split off entries keep their relative order in the usage history, as
+    /// well as their expiration times and pin counts. Keys and values are
+    /// moved, not cloned.
+    ///
+    /// The new cache is configured as with [LruCache::split_off_lru].
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: The number of entries to split off.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.insert("banana".to_owned(), "sweet".to_owned()).unwrap();
+    /// let other = cache.split_off_at(2);
+    ///
+    /// assert_eq!(vec![&"apple".to_owned(), &"lemon".to_owned()],
+    ///     other.keys().collect::<Vec<_>>());
+    /// assert_eq!(vec![&"banana".to_owned()],
+    ///     cache.keys().collect::<Vec<_>>());
+    /// ```
+    pub fn split_off_at(&mut self, n: usize) -> LruCache<K, V, S, W> {
+        self.split_off_lru_entries(n.min(self.len()))
+    }
+}
+
+struct EntryTooLarge<K, V> {
+    key: K,
+    value: V,
+    entry_size: usize,
+    max_size: usize
+}
+
+impl<K, V> From<EntryTooLarge<K, V>> for InsertError<K, V> {
+    fn from(data: EntryTooLarge<K, V>) -> InsertError<K, V> {
+        InsertError::EntryTooLarge {
+            key: data.key,
+            value: data.value,
+            entry_size: data.entry_size,
+            max_size: data.max_size
+        }
+    }
+}
+
+impl<K, V> From<EntryTooLarge<K, V>> for TryInsertError<K, V> {
+    fn from(data: EntryTooLarge<K, V>) -> TryInsertError<K, V> {
+        TryInsertError::EntryTooLarge {
+            key: data.key,
+            value: data.value,
+            entry_size: data.entry_size,
+            max_size: data.max_size
+        }
+    }
+}
+
+struct WouldEjectPinned<K, V> {
+    key: K,
+    value: V,
+    entry_size: usize,
+    available_memory: usize
+}
+
+impl<K, V> From<WouldEjectPinned<K, V>> for InsertError<K, V> {
+    fn from(data: WouldEjectPinned<K, V>) -> InsertError<K, V> {
+        InsertError::WouldEjectPinned {
+            key: data.key,
+            value: data.value,
+            entry_size: data.entry_size,
+            available_memory: data.available_memory
+        }
+    }
+}
+
+impl<K, V> From<WouldEjectPinned<K, V>> for TryInsertError<K, V> {
+    fn from(data: WouldEjectPinned<K, V>) -> TryInsertError<K, V> {
+        TryInsertError::WouldEjectPinned {
+            key: data.key,
+            value: data.value,
+            entry_size: data.entry_size,
+            available_memory: data.available_memory
+        }
+    }
+}
+
+impl<K, V, S, W> LruCache<K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    /// Safety: Requires the pointer to point to an entry contained in this
+    /// cache.
+    unsafe fn weigh_ptr(&self, entry_ptr: EntryPtr<K, V>) -> usize {
+        let entry = entry_ptr.get();
+        self.weigher.weigh(entry.key(), entry.value())
+    }
+
+    fn prepare_insert(&mut self, key: K, value: V)
+            -> Result<UnhingedEntry<K, V>, EntryTooLarge<K, V>> {
+        let entry_size = self.weigher.weigh(&key, &value);
+        self.prepare_insert_with_size(key, value, entry_size)
+    }
+
+    fn prepare_insert_with_size(&self, key: K, value: V, entry_size: usize)
+            -> Result<UnhingedEntry<K, V>, EntryTooLarge<K, V>> {
+        let entry = UnhingedEntry::new(key, value, entry_size);
+
+        if entry_size > self.max_size {
+            let (key, value) = entry.into_key_value();
+
+            Err(EntryTooLarge {
+                key,
+                value,
+                entry_size,
+                max_size: self.max_size
+            })
+        }
+        else {
+            Ok(entry)
+        }
+    }
+
+    fn check_available_memory(&self, entry: UnhingedEntry<K, V>,
+            available_memory: usize)
+            -> Result<UnhingedEntry<K, V>, WouldEjectPinned<K, V>> {
+        let entry_size = entry.size();
+
+        if entry_size > available_memory {
+            let (key, value) = entry.into_key_value();
+
+            Err(WouldEjectPinned {
+                key,
+                value,
+                entry_size,
+                available_memory
+            })
+        }
+        else {
+            Ok(entry)
+        }
+    }
+
+    fn insert_unchecked(&mut self, entry: UnhingedEntry<K, V>, hash: u64,
+            ttl: Option<Duration>) -> EntryPtr<K, V> {
+        let size = entry.size();
+        let mut entry = Entry::new(entry, self.seal, self.seal.get().next);
+        let meta = EntryMeta {
+            expires_at: self.expiry_for(ttl),
+            ..EntryMeta::default()
+        };
+
+        loop {
+            match self.insert_into_table_with_hash(hash, entry, meta) {
+                Ok(entry_ptr) => {
+                    self.current_size += size;
+                    self.set_head(entry_ptr);
+                    unsafe { self.record_access(entry_ptr); }
+                    return entry_ptr;
+                },
+                Err(returned_entry) => {
+                    entry = returned_entry;
+                    self.reallocate((self.table.capacity() * 2).max(1));
+                    
+                    // The seal pointer stays constant through reallocation, so
+                    // only entry.next has to be set.
+
+                    entry.next = self.seal.get().next;
+                }
+            }
+        }
+    }
+
+    /// Inserts a new entry into this cache. This is initially the
+    /// most-recently-used entry. If there was an entry with the given key
+    /// before, it is removed and its value returned. Otherwise, `None` is
+    /// returned. If inserting this entry would violate the memory limit,
+    /// the least-recently-used values are ejected from the cache until it
+    /// fits. If the replaced entry was pinned (see [LruCache::pin]), the new
+    /// entry keeps its pins.
+    ///
+    /// If you want to know before calling this method whether elements would
+    /// be ejected, you can use [entry_size] to obtain the memory usage that
+    /// would be assigned to the created entry and check using
+    /// [LruCache::current_size] and [LruCache::max_size] whether it fits.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// * Raises an [InsertError::EntryTooLarge] if the entry alone would
+    ///   already be too large to fit inside the cache's size limit. That is,
+    ///   even if all other entries were ejected, it still would not be able to
+    ///   be inserted.
+    /// * Otherwise, raises an [InsertError::WouldEjectPinned] if the entry does
+    ///   not fit within the memory of the cache not occupied by pinned entries
+    ///   (see [LruCache::pin]), other than the one it would replace.
+    ///
+    /// If any error was raised, the entry was not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(2, cache.len());
+    /// ```
+    pub fn insert(&mut self, key: K, value: V)
+            -> Result<Option<V>, InsertError<K, V>> {
+        self.insert_with(key, value, self.default_ttl, Self::drop_ejected)
+    }
+
+    /// Inserts a new entry into this cache just like [LruCache::insert], but
+    /// the entry expires once the given time-to-live has elapsed, overriding
+    /// the default time-to-live of the cache (see
+    /// [LruCache::set_default_ttl]). Time is measured by the clock of the
+    /// cache (see [LruCache::set_clock]).
+    ///
+    /// Expired entries are treated as if they were absent. Methods with
+    /// mutable access to the cache, such as [LruCache::get],
+    /// [LruCache::touch], or [LruCache::mutate], remove any expired entry they
+    /// encounter and reclaim its memory. Methods with immutable access, such
+    /// as [LruCache::peek] or [LruCache::contains], cannot do so, so the
+    /// memory is reclaimed only later. Until then, expired entries still count
+    /// towards [LruCache::len] and [LruCache::current_size], are ejected as
+    /// usual, and are visited by iterators. Use [LruCache::purge_expired] to
+    /// remove all expired entries at once. Pinned entries (see
+    /// [LruCache::pin]) do not expire.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    /// * `ttl`: The duration after which the inserted entry expires.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError] under the same conditions as
+    /// [LruCache::insert]. If this occurs, the entry was not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert_with_ttl("apple".to_owned(), "sweet".to_owned(),
+    ///     Duration::from_secs(3600)).unwrap();
+    /// cache.insert_with_ttl("lemon".to_owned(), "sour".to_owned(),
+    ///     Duration::ZERO).unwrap();
+    ///
+    /// assert_eq!(Some(&"sweet".to_owned()), cache.get("apple"));
+    /// assert_eq!(None, cache.get("lemon"));
+    /// assert_eq!(1, cache.len());
+    /// ```
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration)
+            -> Result<Option<V>, InsertError<K, V>> {
+        self.insert_with(key, value, Some(ttl), Self::drop_ejected)
+    }
+
+    /// Inserts a new entry into this cache just like [LruCache::insert], but
+    /// the entry is assigned the given size instead of the one determined by
+    /// the weigher of this cache (see [LruCache::with_weigher]). This is useful
+    /// if only the caller knows what an entry really costs, e.g. if the value
+    /// is the last owner of a large shared buffer.
+    ///
+    /// Operations that mutate the value, such as [LruCache::mutate],
+    /// [LruCache::get_mut], or [LruCache::iter_mut], adjust the size by the
+    /// change of the weight the weigher reports. Use
+    /// [LruCache::mutate_with_size] to keep control over the size.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    /// * `size`: The size to assign to the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError] under the same conditions as
+    /// [LruCache::insert], using the given size. If this occurs, the entry was
+    /// not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert_with_size("apple".to_owned(), "sweet".to_owned(), 512)
+    ///     .unwrap();
+    /// cache.insert_with_size("lemon".to_owned(), "sour".to_owned(), 768)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(1, cache.len());
+    /// assert_eq!(768, cache.current_size());
+    /// ```
+    pub fn insert_with_size(&mut self, key: K, value: V, size: usize)
+            -> Result<Option<V>, InsertError<K, V>> {
+        let entry = self.prepare_insert_with_size(key, value, size)?;
+        self.insert_prepared(entry, self.default_ttl, Self::drop_ejected)
+    }
+
+    /// Inserts a new entry into this cache just like [LruCache::insert], but
+    /// the entry is initially the least-recently-used one instead of the
+    /// most-recently-used one. That is, unless it is used, it is the first
+    /// entry to be ejected. This is useful for scan-like traffic, which should
+    /// not displace entries that are used regularly.
+    ///
+    /// If a time-to-idle is set (see [LruCache::set_time_to_idle]), the entry
+    /// starts with the idle time of the previous least-recently-used entry,
+    /// just as if it was [demoted](LruCache::demote) after insertion.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError] under the same conditions as
+    /// [LruCache::insert]. If this occurs, the entry was not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert_cold("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some((&"lemon".to_owned(), &"sour".to_owned())),
+    ///     cache.peek_lru());
+    /// ```
+    pub fn insert_cold(&mut self, key: K, value: V)
+            -> Result<Option<V>, InsertError<K, V>> {
+        let result = self.insert_with(key, value, self.default_ttl,
+            Self::drop_ejected)?;
+
+        // The inserted entry is most-recently-used after insert_with.
+
+        self.demote_ptr(self.seal.get().next);
+        Ok(result)
+    }
+
+    /// Inserts a new entry into this cache just like [LruCache::insert], but
+    /// additionally returns all entries that were ejected to make room for
+    /// it. These are ordered from least- to most-recently-used. An entry which
+    /// was replaced because it had the same key is not contained in them, but
+    /// its value is returned as the first element of the tuple, as with
+    /// [LruCache::insert].
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError] under the same conditions as
+    /// [LruCache::insert]. If this occurs, the entry was not inserted and no
+    /// entries were ejected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.set_max_size(cache.current_size());
+    /// let (replaced, ejected) = cache
+    ///     .insert_returning_evicted("peach".to_owned(), "sweet".to_owned())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(None, replaced);
+    /// assert_eq!(vec![("apple".to_owned(), "sweet".to_owned())], ejected);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn insert_returning_evicted(&mut self, key: K, value: V)
+            -> Result<(Option<V>, Vec<(K, V)>), InsertError<K, V>> {
+        let mut ejected = Vec::new();
+        let result = self.insert_with(key, value, self.default_ttl,
+            |_, key, value| ejected.push((key, value)))?;
+
+        Ok((result, ejected))
+    }
+
+    /// Inserts all entries of the given iterator in order as with
+    /// [LruCache::insert], passing the error of each rejected entry to
+    /// `on_reject`. Capacity is reserved for the entries up front, but never
+    /// for more than `max_entries` entries in total, which should be the most
+    /// that could possibly fit inside the cache.
+    fn extend_with<I, F>(&mut self, iter: I, max_entries: usize,
+        mut on_reject: F)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        F: FnMut(InsertError<K, V>)
+    {
+        let iter = iter.into_iter();
+        let additional =
+            iter.size_hint().0.min(max_entries.saturating_sub(self.len()));
+        self.reserve(additional);
+
+        for (key, value) in iter {
+            if let Err(err) = self.insert(key, value) {
+                on_reject(err);
+            }
+        }
+    }
+
+    fn insert_with<F>(&mut self, key: K, value: V, ttl: Option<Duration>,
+        on_eject: F) -> Result<Option<V>, InsertError<K, V>>
+    where
+        F: FnMut(&mut Self, K, V)
+    {
+        let entry = self.prepare_insert(key, value)?;
+        self.insert_prepared(entry, ttl, on_eject)
+    }
+
+    fn insert_prepared<F>(&mut self, entry: UnhingedEntry<K, V>,
+        ttl: Option<Duration>, on_eject: F)
+        -> Result<Option<V>, InsertError<K, V>>
+    where
+        F: FnMut(&mut Self, K, V)
+    {
+        // Check that the entry fits beside pinned entries, not counting a
+        // replaced one
+
+        let key = entry.key();
+        let hash = make_insert_hash::<K, S>(&self.hash_builder, key);
+        let replaced = self.table.find(hash, equivalent_key(key));
+        let mut available_memory = self.available_memory();
+
+        if let Some(bucket) = &replaced {
+            unsafe {
+                if self.bucket_meta(bucket).pins > 0 {
+                    available_memory += bucket.as_ref().size;
+                }
+            }
+        }
+
+        let entry = self.check_available_memory(entry, available_memory)?;
+
+        // Deduplicate keys, make space
+
+        let mut pins = 0;
+        let result = replaced.and_then(|bucket| unsafe {
+            if self.is_bucket_expired(&bucket) {
+                self.remove_bucket(bucket, RemovalCause::Expired);
+                None
+            }
+            else {
+                pins = self.bucket_meta(&bucket).pins;
+                Some(self.take_bucket(bucket).1)
+            }
+        });
+        self.eject_to_target_with(self.max_size - entry.size(), on_eject);
+
+        // Insert entry at head of list, keeping the pins of a replaced entry
+
+        let entry_ptr = self.insert_unchecked(entry, hash, ttl);
+
+        if pins > 0 {
+            unsafe { self.meta_mut(entry_ptr).pins = pins; }
+            self.pinned_size += entry_ptr.get().size;
+        }
+
+        Ok(result)
+    }
+
+    /// Tries to insert a new entry into this cache. This is initially the
+    /// most-recently-used entry. If there was an entry with the given key
+    /// before or it does not fit within the memory requirement, an appropriate
+    /// error is raised (see below).
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// * Raises an [TryInsertError::EntryTooLarge] if the entry alone would
+    /// already be too large to fit inside the cache's size limit.
+    /// * Otherwise, raises a [TryInsertError::WouldEjectPinned] if the entry
+    ///   does not fit within the memory of the cache not occupied by pinned
+    ///   entries (see [LruCache::pin]).
+    /// * Otherwise, raises a [TryInsertError::WouldEjectLru] if the entry does
+    /// not fit within the remaining free memory of the cache, i.e. the
+    /// difference between [LruCache::max_size] and [LruCache::current_size].
+    /// * Otherwise, raises an [TryInsertError::OccupiedEntry] if there was
+    /// already an entry with the given key.
+    ///
+    /// If any error was raised, the entry was not inserted into the cache. An
+    /// expired entry with the given key (see [LruCache::insert_with_ttl]) is
+    /// removed in any case and does not count as occupying the key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// let result = cache.try_insert("apple".to_owned(), "sweet".to_owned());
+    /// assert!(result.is_ok());
+    /// let result = cache.try_insert("apple".to_owned(), "sour".to_owned());
+    /// assert!(!result.is_ok());
+    /// ```
+    pub fn try_insert(&mut self, key: K, value: V)
+            -> Result<(), TryInsertError<K, V>> {
+        let entry = self.prepare_insert(key, value)?;
+        self.try_insert_prepared(entry)
+    }
+
+    /// Tries to insert a new entry into this cache just like
+    /// [LruCache::try_insert], but the entry is assigned the given size
+    /// instead of the one determined by the weigher of this cache, as with
+    /// [LruCache::insert_with_size].
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key by which the inserted entry will be identified.
+    /// * `value`: The value to store in the inserted entry.
+    /// * `size`: The size to assign to the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// Raises a [TryInsertError] under the same conditions as
+    /// [LruCache::try_insert], using the given size. If this occurs, the
+    /// entry was not inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, TryInsertError};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.try_insert_with_size("apple".to_owned(), "sweet".to_owned(), 768)
+    ///     .unwrap();
+    /// let result = cache.try_insert_with_size(
+    ///     "lemon".to_owned(), "sour".to_owned(), 512);
+    ///
+    /// assert!(matches!(result, Err(TryInsertError::WouldEjectLru { .. })));
+    /// assert_eq!(768, cache.current_size());
+    /// ```
+    pub fn try_insert_with_size(&mut self, key: K, value: V, size: usize)
+            -> Result<(), TryInsertError<K, V>> {
+        let entry = self.prepare_insert_with_size(key, value, size)?;
+        self.try_insert_prepared(entry)
+    }
+
+    fn try_insert_prepared(&mut self, entry: UnhingedEntry<K, V>)
+            -> Result<(), TryInsertError<K, V>> {
+
+        // Remove an expired entry with the same key, so it neither occupies
+        // the key nor memory
+
+        let key = entry.key();
+        let hash = make_insert_hash::<K, S>(&self.hash_builder, key);
+
+        if let Some(bucket) = self.table.find(hash, equivalent_key(key)) {
+            unsafe {
+                if self.is_bucket_expired(&bucket) {
+                    self.remove_bucket(bucket, RemovalCause::Expired);
+                }
+            }
+        }
+
+        let entry =
+            self.check_available_memory(entry, self.available_memory())?;
+
+        // Check that the entry fits
+
+        let free_memory = self.max_size.saturating_sub(self.current_size);
+        let entry_size = entry.size();
+
+        if entry_size > free_memory {
+            let (key, value) = entry.into_key_value();
+
+            return Err(TryInsertError::WouldEjectLru {
+                key,
+                value,
+                entry_size,
+                free_memory
+            })
+        }
+
+        // Check that the entry is not occupied
+
+        let key = entry.key();
+
+        if self.table.find(hash, equivalent_key(key)).is_some() {
+            let (key, value) = entry.into_key_value();
+
+            return Err(TryInsertError::OccupiedEntry {
+                key,
+                value
+            })
+        }
+
+        self.insert_unchecked(entry, hash, self.default_ttl);
+        Ok(())
+    }
+
+    /// Gets the given key's corresponding entry in this cache for in-place
+    /// manipulation. The key is hashed only once, and the resulting
+    /// [CacheEntry] can be used to inspect, modify, insert, or remove the
+    /// associated value without any further lookups.
+    ///
+    /// If there is an entry for the given key, it is marked as
+    /// most-recently-used by this method. If that entry is expired (see
+    /// [LruCache::insert_with_ttl]), it is removed instead and a vacant entry
+    /// is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry to get.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), 1).unwrap();
+    ///
+    /// for fruit in ["apple", "lemon", "apple"] {
+    ///     cache.entry(fruit.to_owned())
+    ///         .and_modify(|count| *count += 1)
+    ///         .unwrap()
+    ///         .or_insert(1)
+    ///         .unwrap();
+    /// }
+    ///
+    /// assert_eq!(Some(&3), cache.peek("apple"));
+    /// assert_eq!(Some(&1), cache.peek("lemon"));
+    /// ```
+    pub fn entry(&mut self, key: K) -> CacheEntry<'_, K, V, S, W> {
+        let hash = make_insert_hash::<K, S>(&self.hash_builder, &key);
+
+        match self.table.find(hash, equivalent_key(&key)) {
+            Some(bucket) if unsafe { self.is_bucket_expired(&bucket) } => {
+                unsafe { self.remove_bucket(bucket, RemovalCause::Expired); }
+                CacheEntry::Vacant(VacantEntry::new(self, key, hash))
+            },
+            Some(bucket) => {
+                self.touch_ptr(EntryPtr::new(bucket.as_ptr()));
+                CacheEntry::Occupied(OccupiedEntry::new(self, key, bucket))
+            },
+            None => CacheEntry::Vacant(VacantEntry::new(self, key, hash))
+        }
+    }
+
+    /// Gets a reference to the value associated with the given key. If there
+    /// is no value for that key, the given function is called to compute one,
+    /// which is then inserted as with [LruCache::insert]. The key is hashed
+    /// only once, and the function is only called if the key is missing.
+    ///
+    /// In any case, the value is marked as most-recently-used.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to get or insert.
+    /// * `f`: A function that computes the value to insert if there is no
+    ///   value for the given key.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError::EntryTooLarge] if the computed entry alone
+    /// would already be too large to fit inside the cache's size limit, or an
+    /// [InsertError::WouldEjectPinned] if it does not fit beside the pinned
+    /// entries (see [LruCache::pin]). If this occurs, the entry was not
+    /// inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Ok(&"sweet".to_owned()),
+    ///     cache.get_or_insert_with("apple".to_owned(), || unreachable!()));
+    /// assert_eq!(Ok(&"sour".to_owned()),
+    ///     cache.get_or_insert_with("lemon".to_owned(), || "sour".to_owned()));
+    /// ```
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F)
+        -> Result<&V, InsertError<K, V>>
+    where
+        F: FnOnce() -> V
+    {
+        self.entry(key).or_insert_with(f)
+    }
+
+    /// Gets a reference to the value associated with the given key. If there
+    /// is no value for that key, the given fallible function is called to
+    /// compute one, which is then inserted as with [LruCache::insert]. The key
+    /// is hashed only once, and the function is only called if the key is
+    /// missing.
+    ///
+    /// If a value is returned, it is marked as most-recently-used.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to get or insert.
+    /// * `f`: A function that tries to compute the value to insert if there
+    ///   is no value for the given key.
+    ///
+    /// # Errors
+    ///
+    /// * Forwards any error raised by `f`. In that case, nothing is inserted.
+    /// * Raises an [InsertError], converted into `E`, if the computed entry
+    ///   could not be inserted, as with [LruCache::get_or_insert_with].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{InsertError, LruCache};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Error {
+    ///     NotFound,
+    ///     TooLarge
+    /// }
+    ///
+    /// impl<K, V> From<InsertError<K, V>> for Error {
+    ///     fn from(_: InsertError<K, V>) -> Error {
+    ///         Error::TooLarge
+    ///     }
+    /// }
+    ///
+    /// let mut cache: LruCache<String, String> = LruCache::new(1024);
+    ///
+    /// assert_eq!(Err(Error::NotFound),
+    ///     cache.get_or_try_insert_with("apple".to_owned(),
+    ///         || Err(Error::NotFound)));
+    /// assert_eq!(Ok(&"sour".to_owned()),
+    ///     cache.get_or_try_insert_with("lemon".to_owned(),
+    ///         || Ok::<_, Error>("sour".to_owned())));
+    /// ```
+    pub fn get_or_try_insert_with<F, E>(&mut self, key: K, f: F)
+        -> Result<&V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+        E: From<InsertError<K, V>>
+    {
+        match self.entry(key) {
+            CacheEntry::Occupied(entry) => Ok(entry.into_ref()),
+            CacheEntry::Vacant(entry) => Ok(entry.insert(f()?)?)
+        }
+    }
+
+    /// Applies a mutating function to the value associated with the given key.
+    /// The result of that function is returned. If there is no value for the
+    /// given key, `None` is returned, and the operation is never called.
+    /// Otherwise, the entry is marked as most-recently-used by this method.
+    ///
+    /// Note that the operation may also change the size of the value. After it
+    /// terminates, the internal sizes are updated and, if necessary,
+    /// least-recently-used entries are ejected to restore the memory
+    /// requirement. If the operation increases the size beyond the limit of
+    /// this cache, an error is raised (see below).
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to mutate.
+    /// * `op`: An operation that takes as input a mutable reference to the
+    /// value, mutates it, and returns the desired result. This is forwarded by
+    /// this method to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError::EntryTooLarge] if the operation expanded the
+    /// value so much that the entry no longer fit inside the memory limit of
+    /// the cache, or an [MutateError::WouldEjectPinned] if it no longer fit
+    /// beside the other pinned entries (see [LruCache::pin]). If that is the
+    /// case, the entry is removed and its parts returned in the error data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Ok(Some(())),
+    ///     cache.mutate("apple", |s| s.push_str(" and sour")));
+    /// assert_eq!(Some(&"sweet and sour".to_owned()), cache.peek("apple"));
+    /// ```
+    pub fn mutate<Q, R, F>(&mut self, key: &Q, op: F)
+        -> Result<Option<R>, MutateError<K, V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&mut V) -> R
+    {
+        if let Some(entry_ptr) = self.get_live_ptr(key) {
+            unsafe {
+                self.mutate_ptr(entry_ptr, op, Self::drop_ejected).map(Some)
+            }
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    /// Applies a mutating function to the value associated with the given key
+    /// just like [LruCache::mutate], but instead of weighing the entry anew,
+    /// the size the function returns alongside its result is assigned to the
+    /// entry. This is the counterpart of [LruCache::insert_with_size] for
+    /// entries whose size is managed by the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to mutate.
+    /// * `op`: An operation that takes as input a mutable reference to the
+    ///   value, mutates it, and returns the desired result together with the
+    ///   new size of the entry. The result is forwarded by this method to the
+    ///   caller.
+    ///
+    /// # Errors
+    ///
+    /// Raises a [MutateError] under the same conditions as [LruCache::mutate],
+    /// using the size returned by the operation. If that is the case, the
+    /// entry is removed and its parts returned in the error data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert_with_size("apple".to_owned(), "sweet".to_owned(), 256)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Ok(Some(5)), cache.mutate_with_size("apple", |s| {
+    ///     s.push_str(" and sour");
+    ///     (5, 512)
+    /// }));
+    /// assert_eq!(512, cache.current_size());
+    /// ```
+    pub fn mutate_with_size<Q, R, F>(&mut self, key: &Q, op: F)
+        -> Result<Option<R>, MutateError<K, V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&mut V) -> (R, usize)
+    {
+        if let Some(mut entry_ptr) = self.get_live_ptr(key) {
+            unsafe {
+                let old_size = entry_ptr.get().size;
+                let (result, new_size) = op(entry_ptr.get_mut().value_mut());
+                self.update_weight(entry_ptr, old_size, new_size,
+                    Self::drop_ejected)?;
+                Ok(Some(result))
+            }
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    /// Gets a guard providing mutable access to the value associated with the
+    /// given key. If there is no value for that key, `None` is returned.
+    ///
+    /// Since mutating the value may change its size, the cache is updated
+    /// once the guard is dropped or [committed](ValueGuard::commit). Then, the
+    /// entry is marked as most-recently-used and, if necessary,
+    /// least-recently-used entries are ejected to restore the memory
+    /// requirement, as with [LruCache::mutate]. If the value was expanded
+    /// beyond the limit of this cache, the entry is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to get.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    ///
+    /// if let Some(mut value) = cache.get_mut("apple") {
+    ///     value.push_str(" and sour");
+    /// }
+    ///
+    /// assert_eq!(Some(&"sweet and sour".to_owned()), cache.peek("apple"));
+    /// assert_eq!(Some(("lemon".to_owned(), "sour".to_owned())),
+    ///     cache.remove_lru());
+    /// ```
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<ValueGuard<'_, K, V, S, W>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let entry_ptr = self.get_live_ptr(key)?;
+
+        Some(unsafe { ValueGuard::new(self, entry_ptr) })
+    }
+
+    /// Applies a mutating function to the value associated with the given key
+    /// just like [LruCache::mutate], but additionally returns all entries that
+    /// were ejected to restore the memory requirement after the operation
+    /// expanded the value. These are ordered from least- to
+    /// most-recently-used. If there is no value for the given key, `None` is
+    /// returned, and the operation is never called.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the value to mutate.
+    /// * `op`: An operation that takes as input a mutable reference to the
+    ///   value, mutates it, and returns the desired result. This is forwarded
+    ///   by this method to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError::EntryTooLarge] if the operation expanded the
+    /// value so much that the entry no longer fit inside the memory limit of
+    /// the cache, or an [MutateError::WouldEjectPinned] if it no longer fit
+    /// beside the other pinned entries (see [LruCache::pin]). If that is the
+    /// case, the entry is removed and its parts returned in the error data.
+    /// No other entries are ejected in that case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// cache.set_max_size(cache.current_size());
+    /// let (_, ejected) = cache
+    ///     .mutate_returning_evicted("lemon", |s| s.push_str(" and bitter"))
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(vec![("apple".to_owned(), "sweet".to_owned())], ejected);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn mutate_returning_evicted<Q, R, F>(&mut self, key: &Q, op: F)
+        -> Result<Option<(R, Vec<(K, V)>)>, MutateError<K, V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&mut V) -> R
+    {
+        if let Some(entry_ptr) = self.get_live_ptr(key) {
+            let mut ejected = Vec::new();
+            let result = unsafe {
+                self.mutate_ptr(entry_ptr, op,
+                    |_, key, value| ejected.push((key, value)))?
+            };
+
+            Ok(Some((result, ejected)))
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    /// Applies a mutating function to the values associated with all of the
+    /// given keys at once. The result of that function is returned. If there
+    /// is no value for any of the given keys, or any two keys are equal,
+    /// `None` is returned and the operation is never called. Otherwise, the
+    /// entries are marked as most-recently-used in the order of the given
+    /// keys.
+    ///
+    /// As with [LruCache::mutate], the operation may change the sizes of the
+    /// values. After it terminates, the internal sizes are updated and, if
+    /// necessary, least-recently-used entries are ejected to restore the
+    /// memory requirement. This may include some of the mutated entries if
+    /// they do not fit inside the cache together.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys`: The keys of the values to mutate. These must be pairwise
+    ///   distinct.
+    /// * `op`: An operation that takes as input mutable references to the
+    ///   values in the order of the given keys, mutates them, and returns the
+    ///   desired result. This is forwarded by this method to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError] under the same conditions as
+    /// [LruCache::mutate] for any of the entries, in which case that entry is
+    /// removed. If this occurs for multiple entries, all of them are removed,
+    /// but only the parts of the first one in the order of the given keys are
+    /// returned in the error data. The others are only reported to the removal
+    /// listener (see [LruCache::set_removal_listener]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// cache.insert("lemon".to_owned(), "sour".to_owned()).unwrap();
+    /// let result = cache.get_many_mut(["apple", "lemon"], |[apple, lemon]| {
+    ///     std::mem::swap(apple, lemon);
+    /// });
+    ///
+    /// assert_eq!(Ok(Some(())), result);
+    /// assert_eq!(Some(&"sour".to_owned()), cache.peek("apple"));
+    /// assert_eq!(Ok(None), cache.get_many_mut(["apple", "apple"], |_| ()));
+    /// ```
+    pub fn get_many_mut<Q, R, F, const N: usize>(&mut self, keys: [&Q; N],
+        op: F) -> Result<Option<R>, MutateError<K, V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce([&mut V; N]) -> R
+    {
+        // Removing expired entries does not move any other entry in the
+        // table, so pointers obtained earlier remain valid.
+
+        let entry_ptrs = keys.map(|key| self.get_live_ptr(key));
+
+        if entry_ptrs.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+
+        let entry_ptrs = entry_ptrs.map(|entry_ptr| entry_ptr.unwrap());
+
+        for i in 0..N {
+            if entry_ptrs[..i].contains(&entry_ptrs[i]) {
+                return Ok(None);
+            }
+        }
+
+        unsafe {
+            let old_weights =
+                entry_ptrs.map(|entry_ptr| self.weigh_ptr(entry_ptr));
+            let result = op(entry_ptrs.map(|entry_ptr|
+                entry_ptr.get_extended_mut().value_mut()));
+            let new_weights =
+                entry_ptrs.map(|entry_ptr| self.weigh_ptr(entry_ptr));
+
+            // Shrink entries first, so pinned expanded entries are checked
+            // against the memory freed by the others.
+
+            let shrunk = (0..N)
+                .filter(|&i| new_weights[i] <= old_weights[i]);
+            let expanded = (0..N)
+                .filter(|&i| new_weights[i] > old_weights[i]);
+            let mut removed = [false; N];
+            let mut any_expanded = false;
+            let mut first_err: Option<(usize, MutateError<K, V>)> = None;
+
+            for i in shrunk.chain(expanded) {
+                match self.resize_entry(entry_ptrs[i], old_weights[i],
+                        new_weights[i]) {
+                    Ok(expanded) => any_expanded |= expanded,
+                    Err(err) => {
+                        removed[i] = true;
+
+                        match first_err.take() {
+                            Some((j, first)) if j < i => {
+                                self.drop_too_large(err);
+                                first_err = Some((j, first));
+                            },
+                            Some((_, first)) => {
+                                self.drop_too_large(first);
+                                first_err = Some((i, err));
+                            },
+                            None => first_err = Some((i, err))
+                        }
+                    }
+                }
+            }
+
+            for i in 0..N {
+                if !removed[i] {
+                    self.touch_ptr(entry_ptrs[i]);
+                }
+            }
+
+            if any_expanded {
+                self.eject_to_target(self.max_size, RemovalCause::Ejected);
+            }
+
+            match first_err {
+                Some((_, err)) => Err(err),
+                None => Ok(Some(result))
+            }
+        }
+    }
+
+    /// Retains only the elements which satisfy the predicate, just like
+    /// [LruCache::retain], but the predicate may also mutate the values. The
+    /// elements are visited ordered from least-recently-used to
+    /// most-recently-used, and the usage history is not altered.
+    ///
+    /// After all elements have been visited, the sizes of the retained entries
+    /// are updated and, if necessary, least-recently-used entries are ejected
+    /// to restore the memory requirement. Retained entries which were expanded
+    /// so much that they no longer fit inside the memory limit of the cache or
+    /// beside the other pinned entries (see [LruCache::pin]) are removed, as
+    /// with [LruCache::mutate]. This is reported to the removal listener (see
+    /// [LruCache::set_removal_listener]) with [RemovalCause::EntryTooLarge].
+    ///
+    /// # Arguments
+    ///
+    /// * `pred`: A function which takes as input a reference to the key and a
+    ///   mutable reference to the value of an entry, may mutate the value, and
+    ///   decides whether the entry should remain in the map (`true`) or not
+    ///   (`false`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), vec![1, 2, 3]).unwrap();
+    /// cache.insert("lemon".to_owned(), vec![]).unwrap();
+    /// cache.insert("banana".to_owned(), vec![4, 5]).unwrap();
+    /// cache.retain_mut(|_, v| {
+    ///     v.truncate(1);
+    ///     !v.is_empty()
+    /// });
+    ///
+    /// assert_eq!(2, cache.len());
+    /// assert_eq!(Some(&vec![1]), cache.peek("apple"));
+    /// assert_eq!(None, cache.peek("lemon"));
+    /// assert_eq!(Some(&vec![4]), cache.peek("banana"));
+    /// ```
+    pub fn retain_mut<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&K, &mut V) -> bool
+    {
+        let mut tail = self.seal.get().prev;
+        let mut expanded = false;
+
+        while tail != self.seal {
+            unsafe {
+                let old_weight = self.weigh_ptr(tail);
+                let entry = tail.get_mut();
+                let prev = entry.prev;
+                let (key, value) = entry.key_value_mut();
+
+                if pred(key, value) {
+                    let new_weight = self.weigh_ptr(tail);
+
+                    // Ejection is postponed until all entries are resized, so
+                    // an entry that no longer fits is the only one removed
+                    // here.
+
+                    match self.resize_entry(tail, old_weight, new_weight) {
+                        Ok(resize_expanded) => expanded |= resize_expanded,
+                        Err(err) => self.drop_too_large(err)
+                    }
+                }
+                else {
+                    let (key, value) = self.remove_ptr(tail);
+                    self.notify_removal(key, value, RemovalCause::Filtered);
+                }
+
+                tail = prev;
+            }
+        }
+
+        if expanded {
+            self.eject_to_target(self.max_size, RemovalCause::Ejected);
+        }
+    }
+
+    /// Creates an iterator over mutable references to the values of all
+    /// entries in this cache ordered from least- to most-recently-used,
+    /// without altering the usage history. Expired entries (see
+    /// [LruCache::insert_with_ttl]) are skipped.
+    ///
+    /// Sizes are not updated while iterating. Instead, once the iterator is
+    /// dropped, the size of every entry that was handed out is adjusted by
+    /// the change of its weight. Changes made through the references after
+    /// the iterator is dropped are not accounted for. Since the references may
+    /// outlive the iterator, no entries are removed when it is dropped. If
+    /// any entry was expanded beyond the memory requirement, the cache may
+    /// temporarily exceed its maximum size. Least-recently-used entries are
+    /// then ejected by the next insertion, or immediately by calling
+    /// [LruCache::set_max_size] with the current maximum size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "red".to_owned()).unwrap();
+    /// cache.insert("banana".to_owned(), "yellow".to_owned()).unwrap();
+    /// let old_size = cache.current_size();
+    ///
+    /// for (_, value) in cache.iter_mut() {
+    ///     value.push('!');
+    /// }
+    ///
+    /// assert_eq!(Some(&"red!".to_owned()), cache.peek("apple"));
+    /// assert_eq!(Some(&"yellow!".to_owned()), cache.peek("banana"));
+    /// assert!(cache.current_size() > old_size);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, S, W> {
+        IterMut::new(self)
+    }
+
+    /// Creates an iterator over mutable references to the values of all
+    /// entries in this cache ordered from least- to most-recently-used,
+    /// without altering the usage history. Expired entries are skipped and
+    /// sizes are updated once the iterator is dropped, as with
+    /// [LruCache::iter_mut].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), vec![1, 2, 3]).unwrap();
+    /// cache.insert("banana".to_owned(), vec![4, 5]).unwrap();
+    ///
+    /// cache.values_mut().for_each(|value| {
+    ///     value.pop();
+    /// });
+    ///
+    /// assert_eq!(Some(&vec![1, 2]), cache.peek("apple"));
+    /// assert_eq!(Some(&vec![4]), cache.peek("banana"));
+    /// ```
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, S, W> {
+        ValuesMut::new(self)
+    }
+
+    /// Safety: Requires the pointer to point to an entry contained in this
+    /// cache.
+    unsafe fn mutate_ptr<R, F, E>(&mut self, mut entry_ptr: EntryPtr<K, V>,
+        op: F, on_eject: E) -> Result<R, MutateError<K, V>>
+    where
+        F: FnOnce(&mut V) -> R,
+        E: FnMut(&mut Self, K, V)
+    {
+        let old_weight = self.weigh_ptr(entry_ptr);
+        let result = op(entry_ptr.get_mut().value_mut());
+        let new_weight = self.weigh_ptr(entry_ptr);
+
+        self.update_weight(entry_ptr, old_weight, new_weight,
+            on_eject)?;
+        Ok(result)
+    }
+
+    /// Updates the size of the entry pointed to by the given pointer after its
+    /// weight changed from `old_weight` to `new_weight`. The entry is marked
+    /// as most-recently-used and other entries are ejected if necessary,
+    /// passing them to `on_eject`. If the entry no longer fits in the cache,
+    /// it is removed and returned in the error.
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in this
+    /// cache.
+    unsafe fn update_weight<E>(&mut self, entry_ptr: EntryPtr<K, V>,
+        old_weight: usize, new_weight: usize, on_eject: E)
+        -> Result<(), MutateError<K, V>>
+    where
+        E: FnMut(&mut Self, K, V)
+    {
+        let expanded =
+            self.resize_entry(entry_ptr, old_weight, new_weight)?;
+        self.touch_ptr(entry_ptr);
+
+        if expanded {
+            self.eject_to_target_with(self.max_size, on_eject);
+        }
+
+        Ok(())
+    }
+
+    /// Updates the size of the entry pointed to by the given pointer after its
+    /// weight changed from `old_weight` to `new_weight`, without altering the
+    /// usage history or ejecting other entries. Returns whether the entry was
+    /// expanded, in which case the caller must restore the memory
+    /// requirement. If the entry no longer fits in the cache, it is removed
+    /// and returned in the error, without reporting it to the removal
+    /// listener.
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in this
+    /// cache.
+    unsafe fn resize_entry(&mut self, mut entry_ptr: EntryPtr<K, V>,
+        old_weight: usize, new_weight: usize)
+        -> Result<bool, MutateError<K, V>>
+    {
+        let max_size = self.max_size;
+        let pinned = self.meta(entry_ptr).pins > 0;
+        let entry = entry_ptr.get_mut();
+
+        if new_weight > old_weight {
+            // The operation was expanding; we must ensure it still fits.
+
+            let diff = new_weight - old_weight;
+            let old_entry_size = entry.size;
+            let new_entry_size = old_entry_size + diff;
+            let mut available_memory = self.available_memory();
+
+            if pinned {
+                available_memory += old_entry_size;
+            }
+
+            if new_entry_size > max_size {
+                // The entry is too large after the operation; eject it and
+                // raise according error.
+
+                let (key, value) = self.remove_ptr(entry_ptr);
+
+                return Err(MutateError::EntryTooLarge {
+                    key,
+                    value,
+                    old_entry_size,
+                    new_entry_size,
+                    max_size
+                });
+            }
+
+            if new_entry_size > available_memory {
+                // The entry does not fit beside the pinned entries after the
+                // operation; eject it and raise according error.
+
+                let (key, value) = self.remove_ptr(entry_ptr);
+
+                return Err(MutateError::WouldEjectPinned {
+                    key,
+                    value,
+                    old_entry_size,
+                    new_entry_size,
+                    available_memory
+                });
+            }
+
+            if pinned {
+                self.pinned_size += diff;
+            }
+
+            entry.size = new_entry_size;
+            self.current_size += diff;
+
+            Ok(true)
+        }
+        else {
+            // The operation was non-expanding; everything is ok. The entry
+            // may have been given a smaller size explicitly, so it cannot
+            // shrink below zero.
+
+            let diff = (old_weight - new_weight).min(entry.size);
+
+            if pinned {
+                self.pinned_size -= diff;
+            }
+
+            entry.size -= diff;
+            self.current_size -= diff;
+
+            Ok(false)
+        }
+    }
+
+    /// Updates the size of the entry pointed to by the given pointer after its
+    /// weight changed from `old_weight` to `new_weight`, like
+    /// `resize_entry`, but without removing the entry even if it no longer
+    /// fits in the cache. The current size may then exceed the maximum size
+    /// until entries are ejected, e.g. by the next insertion.
+    ///
+    /// Safety: Requires the pointer to point to an entry contained in this
+    /// cache.
+    unsafe fn reweigh_entry(&mut self, mut entry_ptr: EntryPtr<K, V>,
+            old_weight: usize, new_weight: usize) {
+        let pinned = self.meta(entry_ptr).pins > 0;
+        let entry = entry_ptr.get_mut();
+
+        if new_weight > old_weight {
+            let diff = new_weight - old_weight;
+
+            if pinned {
+                self.pinned_size += diff;
+            }
+
+            entry.size += diff;
+            self.current_size += diff;
+        }
+        else {
+            let diff = (old_weight - new_weight).min(entry.size);
+
+            if pinned {
+                self.pinned_size -= diff;
+            }
+
+            entry.size -= diff;
+            self.current_size -= diff;
+        }
+    }
+}
+
+impl<K, V, S, W> IntoIterator for LruCache<K, V, S, W> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, S, W>;
+
+    fn into_iter(self) -> IntoIter<K, V, S, W> {
+        IntoIter::new(self)
+    }
+}
+
+/// Entries are inserted in iteration order as with [LruCache::insert], so the
+/// last entry becomes the most-recently-used one. Entries that do not fit
+/// inside the cache are skipped. Use [LruCache::from_iter_with_max_size] to
+/// obtain them instead.
+impl<K, V, S, W> Extend<(K, V)> for LruCache<K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        // The weigher may weigh entries in any unit, so only the maximum size
+        // bounds the number of entries, assuming each weighs at least one.
+
+        self.extend_with(iter, self.max_size, |_| { })
+    }
+}
+
+impl<K, V, S, W> Clone for LruCache<K, V, S, W>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    S: BuildHasher + Clone,
+    W: Clone
+{
+    fn clone(&self) -> LruCache<K, V, S, W> {
+        let max_size = self.max_size;
+        let table = RawTable::with_capacity(self.capacity());
+        let hash_builder = self.hash_builder.clone();
+        let weigher = self.weigher.clone();
+        let mut clone = LruCache::with_table_hasher_and_weigher(
+            max_size, table, hash_builder, weigher);
+        clone.current_size = self.current_size;
+        clone.pinned_size = self.pinned_size;
+        clone.default_ttl = self.default_ttl;
+        clone.time_to_idle = self.time_to_idle;
+        clone.clock = self.clock.clone();
+        clone.access_counter = self.access_counter;
+        clone.expiration_used = self.expiration_used;
+        let mut next = self.seal.get().prev;
+
+        while next != self.seal {
+            let (entry, meta) =
+                unsafe { (next.get().clone(), *self.meta(next)) };
+            next = entry.prev;
+            clone.insert_untracked(entry, meta);
+        }
+
+        clone
+    }
+}
+
+impl<K, V, S, W> Drop for LruCache<K, V, S, W> {
+    fn drop(&mut self) {
+        for entry in self.table.drain() {
+            unsafe { entry.drop() };
+        }
+
+        unsafe {
+            self.seal.drop_seal();
+        }
+    }
+}
+
+impl<K: Debug, V: Debug, S, W> Debug for LruCache<K, V, S, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// Since the LruCache contains raw pointers, it is not automatically marked as
+// Send and Sync. We will provide manual implementations as well as arguments
+// why that is ok.
+
+// It is implicitly assumed that every LruCache only contains pointers to
+// memory that belongs to the cache itself. So, if an LruCache is sent to
+// another thread, that memory can now only be accessed by that thread. In
+// other words, two LruCaches or anything related (e.g. iterators) can never
+// access the same memory. Therefore, sending them is no issue.
+
+unsafe impl<K: Send, V: Send, S: Send, W: Send> Send
+    for LruCache<K, V, S, W> { }
+
+// If an immutable reference to an LruCache exists, there is simultaneously no
+// mutable reference to the same cache. By design of the cache, any operations
+// applied to that reference will allow no writing access to any of its memory.
+// Those that yield any possibility of writing, such as cloning, are restricted
+// to newly allocated memory. Therefore, sending references is no issue, and by
+// definition of Sync, LruCache may implement it. The removal listener is only
+// ever accessed through a mutable reference, so it does not need to be Sync.
+// The clock is required to be Send and Sync by the Clock trait.
+
+unsafe impl<K: Sync, V: Sync, S: Sync, W: Sync> Sync
+    for LruCache<K, V, S, W> { }
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+    use std::sync::{Arc, Mutex};
+    use super::*;
+
+    pub(crate) fn singleton_test_cache() -> LruCache<&'static str, &'static str> {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache
+    }
+
+    pub(crate) fn large_test_cache() -> LruCache<&'static str, &'static str> {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        cache.insert("ahoy", "mars").unwrap();
+        cache.insert("hi", "venus").unwrap();
+        cache.insert("good morning", "jupiter").unwrap();
+        cache
+    }
+
+    #[test]
+    fn cache_correctly_inserts_with_sufficient_capacity() {
+        let mut cache = LruCache::new(1024);
+    
+        assert_eq!(0, cache.len());
+        assert_eq!(0, cache.current_size());
+        assert_eq!(1024, cache.max_size());
+    
+        assert_eq!(Ok(None),
+            cache.insert("key1".to_owned(), "value1".to_owned()));
+    
+        let new_size = cache.current_size();
+    
+        assert_eq!(1, cache.len());
+        assert!(new_size > 0);
+        assert_eq!(Some(&"value1".to_owned()), cache.get("key1"));
+    
+        assert_eq!(Ok(None),
+            cache.insert("key2".to_owned(), "value2".to_owned()));
+    
+        assert_eq!(2, cache.len());
+        assert!(cache.current_size() > new_size);
+        assert_eq!(Some(&"value1".to_owned()), cache.get("key1"));
+        assert_eq!(Some(&"value2".to_owned()), cache.get("key2"));
+    }
+    
+    #[test]
+    fn cache_deduplicates_keys() {
+        let mut cache = LruCache::new(1024);
+    
+        assert_eq!(Ok(None),
+            cache.insert("key".to_owned(), "value".to_owned()));
+    
+        let expected_size = cache.current_size;
+    
+        assert_eq!(Ok(Some("value".to_owned())),
+            cache.insert("key".to_owned(), "value".to_owned()));
+        assert_eq!(expected_size, cache.current_size());
+        assert_eq!(1, cache.len());
+    }
+    
+    fn string_with_size(size: usize) -> String {
+        let mut s = String::with_capacity(size);
+    
+        for _ in 0..size {
+            s.push('0');
+        }
+    
+        s
+    }
+    
+    #[test]
+    fn cache_ejects_lru_if_overflowing() {
+        let mut cache = LruCache::new(2048);
+    
+        // On 256-bit arch, each entry requires an extra 289 bytes per entry
+        // in addition to the size of the value in bytes (see test case
+        // "entry_correctly_computes_size", value_str_bytes = 1).
+        // On 16-bit arch, this would be 19 bytes.
+        // The numbers in this test case accommodate anywhere from 15 to 696
+        // extra bytes (676 * 3 + 15 = 2050, 676 * 2 + 696 = 2048).
+        
+        cache.insert("a".to_owned(), string_with_size(676)).unwrap();
+        cache.insert("b".to_owned(), string_with_size(676)).unwrap();
+        
+        let expected_size = cache.current_size();
+        
+        cache.insert("c".to_owned(), string_with_size(676)).unwrap();
+        
+        assert_eq!(expected_size, cache.current_size());
+        assert_eq!(2, cache.len());
+        assert!(cache.peek("a").is_none());
+        assert!(cache.peek("b").is_some());
+        assert!(cache.peek("c").is_some());
+        
+        assert_eq!(Some("b"), cache.peek_lru().map(|(key, _)| key.as_str()));
+        assert_eq!(Some("c"), cache.peek_mru().map(|(key, _)| key.as_str()));
+    }
+    
+    #[test]
+    fn getting_sets_most_recently_used() {
+        let mut cache = LruCache::new(2048);
+    
+        // See the argument why the sizes are like this in test case
+        // "cache_ejects_lru_if_overflowing".
+    
+        cache.insert("a".to_owned(), string_with_size(674)).unwrap();
+        cache.insert("b".to_owned(), string_with_size(674)).unwrap();
+        cache.get(&"a".to_owned());
+        cache.insert("c".to_owned(), string_with_size(674)).unwrap();
+    
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    
+        assert_eq!("a", unsafe { cache.seal.get().prev.get().key() });
+        assert_eq!("c", unsafe { cache.seal.get().next.get().key() });
+    
+        cache.get("a");
+    
+        assert_eq!("c", unsafe { cache.seal.get().prev.get().key() });
+        assert_eq!("a", unsafe { cache.seal.get().next.get().key() });
+    }
+
+    #[test]
+    fn empty_cache_has_no_lru_and_mru() {
+        let cache = LruCache::<&str, &str>::new(1024);
+
+        assert!(cache.peek_lru().is_none());
+        assert!(cache.peek_mru().is_none());
+    }
+
+    #[test]
+    fn get_lru_sets_most_recently_used() {
+        let mut cache = LruCache::new(2048);
+
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+
+        let lru = cache.get_lru();
+
+        assert_eq!(Some((&"hello", &"world")), lru);
+
+        cache.set_max_size(cache.current_size() - 1);
+
+        assert!(cache.contains("hello"));
+        assert!(!cache.contains("greetings"));
+    }
+    
+    #[test]
+    fn peeking_does_not_set_most_recently_used() {
+        let mut cache = LruCache::new(2048);
+    
+        cache.insert("a".to_owned(), string_with_size(674)).unwrap();
+        cache.insert("b".to_owned(), string_with_size(674)).unwrap();
+        cache.peek(&"a".to_owned());
+        cache.insert("c".to_owned(), string_with_size(674)).unwrap();
+    
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+        assert!(cache.get("c").is_some());
+
+        cache.peek_entry(&"b".to_owned());
+        cache.insert("d".to_owned(), string_with_size(674)).unwrap();
+
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+        assert!(cache.get("d").is_some());
+    }
+    
+    #[test]
+    fn cache_rejects_too_large_entry() {
+        let mut cache = LruCache::new(256);
+        let key = "This is a pretty long key, especially considering that \
+            keys should normally be rather small to avoid long hashing times."
+            .to_owned();
+        let value = "Although the key alone has insufficient size, together \
+            with this string it pushes pushes the total memory requirement of the \
+            entry over the capacity of the cache.".to_owned();
+    
+        assert!(matches!(cache.insert(key, value),
+            Err(InsertError::EntryTooLarge { .. })));
+    }
+    
+    #[test]
+    fn precisely_fitting_entry_does_not_eject_lru() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        cache.insert("greetings".to_owned(), "moon".to_owned()).unwrap();
+    
+        let key = "ahoy".to_owned();
+        let mut value = "mars".to_owned();
+        value.shrink_to_fit();
+        let required_size = cache.max_size() - cache.current_size();
+        let additional_bytes = required_size - entry_size(&key, &value);
+        let additional_data = vec![b's'; additional_bytes];
+        value.push_str(&String::from_utf8(additional_data).unwrap());
+        value.shrink_to_fit();
+    
+        assert_eq!(required_size, entry_size(&key, &value));
+    
+        cache.insert(key, value).unwrap();
+    
+        assert_eq!(3, cache.len());
+    }
+
+    #[test]
+    fn auto_reallocation_doubles_capacity() {
+        let mut cache = LruCache::with_capacity(4096, 10);
+        let capacity = cache.capacity();
+
+        for index in 0..capacity {
+            cache.insert(index, 0).unwrap();
+        }
+
+        cache.insert(capacity, 0).unwrap();
+
+        assert_eq!(2 * capacity, cache.capacity());
+    }
+    
+    #[test]
+    fn try_insert_works_as_insert_if_ok() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        cache.try_insert("greetings".to_owned(), "moon".to_owned()).unwrap();
+    
+        assert_eq!(2, cache.len());
+        assert_eq!("world".to_owned(), cache.remove_lru().unwrap().1);
+        assert_eq!("moon".to_owned(), cache.remove_lru().unwrap().1);
+    }
+    
+    #[test]
+    fn try_insert_fails_on_duplication() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        let result = cache.try_insert("hello".to_owned(), "moon".to_owned());
+    
+        assert!(matches!(result, Err(TryInsertError::OccupiedEntry { .. })));
+        assert_eq!(1, cache.len());
+        assert_eq!(&"world".to_owned(), cache.get("hello").unwrap());
+    }
+    
+    #[test]
+    fn try_insert_fails_if_eject_required() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        cache.insert("greetings".to_owned(), "moon".to_owned()).unwrap();
+        cache.set_max_size(cache.current_size());
+        let result = cache.try_insert("ahoy".to_owned(), "mars".to_owned());
+    
+        assert!(matches!(result, Err(TryInsertError::WouldEjectLru { .. })));
+        assert_eq!(2, cache.len());
+        assert!(!cache.contains("ahoy"));
+    }
+    
+    #[test]
+    fn try_insert_fails_if_too_large() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        let value = String::from_utf8(vec![b'0'; 1024]).unwrap();
+        let result = cache.try_insert("key".to_owned(), value);
+    
+        assert!(matches!(result, Err(TryInsertError::EntryTooLarge { .. })));
+        assert_eq!(1, cache.len());
+        assert!(!cache.contains("key"));
+    }
+
+    #[test]
+    fn insert_with_size_uses_given_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert_with_size("hello".to_owned(), "world".to_owned(), 400)
+            .unwrap();
+        cache.insert_with_size("greetings".to_owned(), "moon".to_owned(), 500)
+            .unwrap();
+        cache.insert_with_size("ahoy".to_owned(), "mars".to_owned(), 300)
+            .unwrap();
+
+        assert_eq!(2, cache.len());
+        assert!(!cache.contains("hello"));
+        assert_eq!(800, cache.current_size());
+    }
+
+    #[test]
+    fn insert_with_size_replacing_entry_uses_given_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        let old_value =
+            cache.insert_with_size("hello".to_owned(), "moon".to_owned(), 10)
+                .unwrap();
+
+        assert_eq!(Some("world".to_owned()), old_value);
+        assert_eq!(10, cache.current_size());
+    }
+
+    #[test]
+    fn insert_with_size_fails_if_too_large() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        let result =
+            cache.insert_with_size("key".to_owned(), "value".to_owned(), 1025);
+
+        assert!(matches!(result, Err(InsertError::EntryTooLarge { .. })));
+        assert_eq!(1, cache.len());
+        assert!(!cache.contains("key"));
+    }
+
+    #[test]
+    fn try_insert_with_size_fails_if_eject_required() {
+        let mut cache = LruCache::new(1024);
+        cache.try_insert_with_size("hello".to_owned(), "world".to_owned(), 600)
+            .unwrap();
+        let result = cache.try_insert_with_size(
+            "ahoy".to_owned(), "mars".to_owned(), 600);
+
+        assert!(matches!(result,
+            Err(TryInsertError::WouldEjectLru { entry_size: 600, .. })));
+        assert_eq!(1, cache.len());
+        assert_eq!(600, cache.current_size());
+    }
+
+    #[test]
+    fn mutate_with_size_uses_returned_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert_with_size("hello".to_owned(), "world".to_owned(), 400)
+            .unwrap();
+        cache.insert_with_size("greetings".to_owned(), "moon".to_owned(), 400)
+            .unwrap();
+        let result = cache.mutate_with_size("hello", |value| {
+            value.push('!');
+            (value.len(), 700)
+        });
+
+        assert_eq!(Ok(Some(6)), result);
+        assert_eq!(1, cache.len());
+        assert_eq!(Some(&"world!".to_owned()), cache.peek("hello"));
+        assert_eq!(700, cache.current_size());
+
+        cache.mutate_with_size("hello", |_| ((), 50)).unwrap();
+
+        assert_eq!(50, cache.current_size());
+    }
+
+    #[test]
+    fn mutate_with_size_on_non_existent_entry_returns_none() {
+        let mut cache: LruCache<String, String> = LruCache::new(1024);
+        let result = cache.mutate_with_size("hello", |_| ((), 0));
+
+        assert_eq!(Ok(None), result);
+    }
+
+    #[test]
+    fn mutate_with_size_fails_if_too_large() {
+        let mut cache = LruCache::new(1024);
+        cache.insert_with_size("hello".to_owned(), "world".to_owned(), 400)
+            .unwrap();
+        let result = cache.mutate_with_size("hello", |_| ((), 1025));
+
+        assert!(matches!(result, Err(MutateError::EntryTooLarge {
+            old_entry_size: 400,
+            new_entry_size: 1025,
+            ..
+        })));
+        assert!(cache.is_empty());
+        assert_eq!(0, cache.current_size());
+    }
+
+    #[test]
+    fn mutate_does_not_shrink_explicitly_sized_entry_below_zero() {
+        let mut cache = LruCache::new(1024);
+        let value = String::from_utf8(vec![b'0'; 256]).unwrap();
+        cache.insert_with_size("hello".to_owned(), value, 1).unwrap();
+        cache.mutate("hello", |value| {
+            value.clear();
+            value.shrink_to_fit();
+        }).unwrap();
+
+        assert_eq!(0, cache.current_size());
+        assert_eq!(1, cache.len());
+    }
+    
+    #[test]
+    fn removing_works() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        cache.insert("ahoy", "mars").unwrap();
+    
+        assert_eq!(Some(("hello", "world")), cache.remove_entry("hello"));
+        assert_eq!(None, cache.remove("hello"));
+    }
+
+    #[test]
+    fn removing_mru_works() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        cache.insert("ahoy", "mars").unwrap();
+        cache.remove_mru();
+
+        assert_eq!(2, cache.len());
+        assert!(cache.contains("hello"));
+        assert!(cache.contains("greetings"));
+    }
+
+    #[test]
+    fn clearing_works() {
+        let mut cache = large_test_cache();
+        cache.clear();
+
+        assert!(cache.is_empty());
+        assert!(cache.peek_lru().is_none());
+        assert!(cache.peek_mru().is_none());
+        assert!(cache.iter().next().is_none());
+    }
+    
+    #[test]
+    fn retain_does_not_affect_empty_cache() {
+        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
+        cache.retain(|k, v| v.len() as u64 == *k);
+    
+        assert_eq!(0, cache.len());
+    }
+    
+    #[test]
+    fn retain_works_if_all_match() {
+        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
+        cache.insert(1, vec![0]).unwrap();
+        cache.insert(5, vec![2, 3, 5, 7, 11]).unwrap();
+        cache.insert(4, vec![1, 4, 9, 16]).unwrap();
+        cache.retain(|k, v| v.len() as u64 == *k);
+    
+        assert_eq!(3, cache.len());
+        assert!(cache.contains(&1));
+        assert!(cache.contains(&4));
+        assert!(cache.contains(&5));
+    }
+    
+    #[test]
+    fn retain_works_if_none_match() {
+        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
+        cache.insert(1, vec![0, 1]).unwrap();
+        cache.insert(5, vec![2, 3, 5, 7, 11, 13]).unwrap();
+        cache.insert(4, vec![1, 4, 9, 16, 25]).unwrap();
+        cache.retain(|k, v| v.len() as u64 == *k);
+    
+        assert_eq!(0, cache.len());
+        assert!(!cache.contains(&1));
+        assert!(!cache.contains(&4));
+        assert!(!cache.contains(&5));
+    }
+    
+    #[test]
+    fn retain_works_if_some_match() {
+        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
+        cache.insert(1, vec![0, 1]).unwrap();
+        cache.insert(5, vec![2, 3, 5, 7, 11]).unwrap();
+        cache.insert(4, vec![1, 4, 9, 16]).unwrap();
+        cache.retain(|k, v| v.len() as u64 == *k);
+    
+        assert_eq!(2, cache.len());
+        assert!(!cache.contains(&1));
+        assert!(cache.contains(&4));
+        assert!(cache.contains(&5));
+    }
+
+    #[test]
+    fn retain_sets_lru_and_mru_if_necessary() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(1, 0).unwrap();
+        cache.insert(2, 0).unwrap();
+        cache.insert(3, 0).unwrap();
+        cache.insert(4, 0).unwrap();
+        cache.retain(|&k, _| k != 1 && k != 4);
+
+        assert_eq!(Some((&2, &0)), cache.peek_lru());
+        assert_eq!(Some((&3, &0)), cache.peek_mru());
+    }
+    
+    #[test]
+    fn retain_mut_updates_sizes_of_retained_entries() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        let entry_size = cache.current_size() / 3;
+        cache.retain_mut(|k, v| {
+            v.truncate(*k);
+            v.shrink_to_fit();
+            *k > 0
+        });
+
+        assert_eq!(vec![1, 2], cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(2 * entry_size - 13, cache.current_size());
+    }
+
+    #[test]
+    fn retain_mut_ejects_lru_once_if_overflowing() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        cache.set_max_size(cache.current_size());
+        let mut visited = Vec::new();
+        cache.retain_mut(|k, v| {
+            visited.push(*k);
+
+            if *k == 1 {
+                v.push(0);
+            }
+
+            true
+        });
+
+        assert_eq!(vec![0, 1, 2], visited);
+        assert_eq!(vec![1, 2], cache.keys().copied().collect::<Vec<_>>());
+        assert!(cache.current_size() <= cache.max_size());
+    }
+
+    #[test]
+    fn retain_mut_removes_too_expanded_entry() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.retain_mut(|k, v| {
+            if *k == 0 {
+                v.resize(1024, 0);
+            }
+
+            true
+        });
+
+        assert_eq!(vec![1], cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter_from_yields_entry_and_more_recently_used_ones() {
+        let cache = large_test_cache();
+        let mut iter = cache.iter_from("ahoy").unwrap();
+
+        assert_eq!(Some((&"ahoy", &"mars")), iter.next());
+        assert_eq!(Some((&"good morning", &"jupiter")), iter.next_back());
+        assert_eq!(Some((&"hi", &"venus")), iter.next());
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn iter_from_back_yields_entry_and_less_recently_used_ones() {
+        let cache = large_test_cache();
+        let keys = cache.iter_from_back("greetings").unwrap()
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["hello", "greetings"], keys);
+    }
+
+    #[test]
+    fn iter_from_lru_and_back_from_mru_yield_everything() {
+        let cache = large_test_cache();
+
+        assert_eq!(5, cache.iter_from("hello").unwrap().count());
+        assert_eq!(5, cache.iter_from_back("good morning").unwrap().count());
+        assert_eq!(1, cache.iter_from("good morning").unwrap().count());
+        assert_eq!(1, cache.iter_from_back("hello").unwrap().count());
+    }
+
+    #[derive(Hash)]
+    struct BorrowedPair<'a>(&'a str, u32);
+
+    impl Equivalent<(String, u32)> for BorrowedPair<'_> {
+        fn equivalent(&self, key: &(String, u32)) -> bool {
+            self.0 == key.0 && self.1 == key.1
+        }
+    }
+
+    #[test]
+    fn lookups_with_equivalent_key_work() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(("hello".to_owned(), 0), 1).unwrap();
+        cache.insert(("hello".to_owned(), 1), 2).unwrap();
+        cache.insert(("world".to_owned(), 0), 3).unwrap();
+
+        assert!(cache.contains(&BorrowedPair("hello", 0)));
+        assert!(!cache.contains(&BorrowedPair("world", 1)));
+        assert_eq!(Some(&2), cache.peek(&BorrowedPair("hello", 1)));
+        assert_eq!(Some(&1), cache.get(&BorrowedPair("hello", 0)));
+
+        cache.touch(&BorrowedPair("world", 0));
+
+        assert_eq!(Some((&("world".to_owned(), 0), &3)), cache.peek_mru());
+
+        cache.mutate(&BorrowedPair("hello", 1), |v| *v += 1).unwrap();
+
+        assert_eq!(Some(3), cache.remove(&BorrowedPair("hello", 1)));
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn contains_works() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+    
+        assert!(cache.contains("hello"));
+        assert!(!cache.contains("greetings"));
+    }
+    
+    #[test]
+    fn increasing_max_size_keeps_all_elements() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        cache.set_max_size(2048);
+    
+        assert_eq!(2, cache.len());
+        assert_eq!(2048, cache.max_size());
+    }
+    
+    #[test]
+    fn decreasing_max_size_below_current_size_drops_elements() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        cache.set_max_size(cache.current_size() - 1);
+    
+        assert_eq!(1, cache.len());
+        assert!(cache.current_size() < cache.max_size());
+        assert!(cache.max_size() < 1024);
+    }
+    
+    #[test]
+    fn resize_yields_ejected_entries_in_lru_order() {
+        let mut cache = large_test_cache();
+        cache.pin("greetings");
+        let max_size = cache.current_size() / 2;
+        let ejected = cache.resize(max_size).collect::<Vec<_>>();
+
+        assert_eq!(vec![("hello", "world"), ("ahoy", "mars"), ("hi", "venus")],
+            ejected);
+        assert_eq!(max_size, cache.max_size());
+        assert!(cache.current_size() <= max_size);
+        assert!(cache.contains("greetings"));
+    }
+
+    #[test]
+    fn resize_to_larger_size_yields_nothing() {
+        let mut cache = large_test_cache();
+
+        assert_eq!(0, cache.resize(2048).count());
+        assert_eq!(5, cache.len());
+        assert_eq!(2048, cache.max_size());
+    }
+
+    #[test]
+    fn evict_to_reports_freed_bytes_and_keeps_max_size() {
+        let mut cache = large_test_cache();
+        cache.pin("hello");
+        let size_before = cache.current_size();
+        let entry_size = size_before / 5;
+        let (ejected, freed) = cache.evict_to(size_before - entry_size - 1);
+
+        assert_eq!(vec![("greetings", "moon"), ("ahoy", "mars")],
+            ejected.collect::<Vec<_>>());
+        assert_eq!(2 * entry_size, freed);
+        assert_eq!(size_before - freed, cache.current_size());
+        assert_eq!(1024, cache.max_size());
+    }
+
+    #[test]
+    fn evict_bytes_frees_at_most_unpinned_entries() {
+        let mut cache = large_test_cache();
+        cache.pin("hi");
+        let pinned_size = cache.pinned_size();
+        let (ejected, freed) = cache.evict_bytes(usize::MAX);
+
+        drop(ejected);
+
+        assert_eq!(vec!["hi"], cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(pinned_size, cache.current_size());
+        assert_eq!(4 * pinned_size, freed);
+    }
+
+    #[test]
+    fn rank_of_counts_more_recently_used_entries() {
+        let mut cache = large_test_cache();
+        let entry_size = cache.current_size() / 5;
+
+        assert_eq!(Some((4, 4 * entry_size)), cache.rank_of("hello"));
+        assert_eq!(Some((2, 2 * entry_size)), cache.rank_of("ahoy"));
+        assert_eq!(Some((0, 0)), cache.rank_of("good morning"));
+        assert_eq!(None, cache.rank_of("bonjour"));
+
+        cache.touch("hello");
+
+        assert_eq!(Some((0, 0)), cache.rank_of("hello"));
+    }
+
+    #[test]
+    fn nth_lru_and_nth_mru_agree_with_iteration_order() {
+        let cache = large_test_cache();
+        let keys = cache.keys().copied().collect::<Vec<_>>();
+
+        for (n, key) in keys.iter().enumerate() {
+            assert_eq!(Some(key), cache.nth_lru(n).map(|(k, _)| k));
+            assert_eq!(Some(key), cache.nth_mru(4 - n).map(|(k, _)| k));
+        }
+
+        assert_eq!(None, cache.nth_lru(5));
+        assert_eq!(None, cache.nth_mru(5));
+    }
+
+    #[test]
+    fn cache_correctly_applies_mutation() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        cache.insert("greetings".to_owned(), "moon".to_owned()).unwrap();
+    
+        let old_size = cache.current_size();
+        let result = cache.mutate("greetings", |s| {
+            s.push_str(", from 384400 km away");
+            s.shrink_to_fit();
+            s.len()
+        });
+    
+        assert_eq!(Ok(Some(25)), result);
+        assert_eq!(2, cache.len());
+        assert_eq!(old_size + 21, cache.current_size());
+        assert_eq!(Some(&"moon, from 384400 km away".to_owned()),
+            cache.get("greetings"));
+    }
+    
+    #[test]
+    fn cache_rejects_too_expanding_mutation() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", vec![0u8; 32]).unwrap();
+        cache.insert("greetings", vec![0u8; 32]).unwrap();
+    
+        let old_size = cache.current_size();
+        let result = cache.mutate("hello", |v| {
+            v.append(&mut vec![0u8; 1000]);
+        });
+    
+        assert!(matches!(result, Err(MutateError::EntryTooLarge { .. })));
+        assert_eq!(1, cache.len());
+        assert!(cache.current_size() < old_size);
+        assert_eq!(None, cache.get("hello"));
+    }
+
+    #[test]
+    fn non_expanding_mutation_works() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", vec![0u8; 32]).unwrap();
+        cache.insert("greetings", vec![0u8; 32]).unwrap();
+
+        let old_size = cache.current_size();
+        let result = cache.mutate("hello", |v| {
+            *v = Vec::new();
+        });
+
+        assert!(matches!(result, Ok(Some(_))));
+        assert!(cache.current_size() < old_size);
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn mutation_on_non_existent_element_is_never_called() {
+        let mut cache = LruCache::<&str, &str>::new(1024);
+        let result = cache.mutate("hello", |_| { panic!("mutation was called") });
+
+        assert_eq!(Ok(None), result);
+    }
+    
+    #[test]
+    fn insert_returning_evicted_returns_ejected_entries_in_lru_order() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 32]).unwrap();
+        cache.insert(1, vec![0u8; 32]).unwrap();
+        cache.insert(2, vec![0u8; 32]).unwrap();
+        let entry_size = cache.current_size() / 3;
+        cache.set_max_size(cache.current_size());
+        let (replaced, ejected) = cache
+            .insert_returning_evicted(3, vec![0u8; 32 + entry_size])
+            .unwrap();
+
+        assert_eq!(None, replaced);
+        assert_eq!(vec![(0, vec![0u8; 32]), (1, vec![0u8; 32])], ejected);
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn insert_returning_evicted_does_not_return_replaced_entry() {
+        let mut cache = large_test_cache();
+        let (replaced, ejected) =
+            cache.insert_returning_evicted("hello", "sun").unwrap();
+
+        assert_eq!(Some("world"), replaced);
+        assert!(ejected.is_empty());
+        assert_eq!(5, cache.len());
+    }
+
+    #[test]
+    fn mutate_returning_evicted_returns_ejected_entries_in_lru_order() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 32]).unwrap();
+        cache.insert(1, vec![0u8; 32]).unwrap();
+        cache.insert(2, vec![0u8; 32]).unwrap();
+        let entry_size = cache.current_size() / 3;
+        cache.set_max_size(cache.current_size());
+        let (len, ejected) = cache
+            .mutate_returning_evicted(&2, |v| {
+                v.resize(32 + 2 * entry_size, 0);
+                v.shrink_to_fit();
+                v.len()
+            })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(32 + 2 * entry_size, len);
+        assert_eq!(vec![(0, vec![0u8; 32]), (1, vec![0u8; 32])], ejected);
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn mutate_returning_evicted_on_non_existent_element_returns_none() {
+        let mut cache = LruCache::<&str, &str>::new(1024);
+        let result = cache.mutate_returning_evicted("hello", |_| {
+            panic!("mutation was called")
+        });
+
+        assert_eq!(Ok(None), result);
+    }
+
+    #[test]
+    fn get_many_touches_hits_in_order() {
+        let mut cache = large_test_cache();
+        let values = cache.get_many(["ahoy", "bonjour", "hello"]);
+
+        assert_eq!([Some(&"mars"), None, Some(&"world")], values);
+        assert_eq!(Some((&"hello", &"world")), cache.peek_mru());
+        assert_eq!(vec!["greetings", "hi", "good morning", "ahoy", "hello"],
+            cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn get_many_mut_returns_none_for_missing_or_duplicate_keys() {
+        let mut cache = large_test_cache();
+
+        assert_eq!(Ok(None), cache.get_many_mut(["hello", "bonjour"],
+            |_| panic!("function was called")));
+        assert_eq!(Ok(None), cache.get_many_mut(["hello", "hi", "hello"],
+            |_| panic!("function was called")));
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+    }
+
+    #[test]
+    fn get_many_mut_updates_sizes_and_ejects_others() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        cache.set_max_size(cache.current_size());
+        let result = cache.get_many_mut([&2, &1], |[a, b]| {
+            a.truncate(0);
+            a.shrink_to_fit();
+            b.extend_from_slice(&[1u8; 16]);
+            b.shrink_to_fit();
+        });
+
+        assert_eq!(Ok(Some(())), result);
+        assert_eq!(vec![2, 1], cache.keys().copied().collect::<Vec<_>>());
+        assert!(cache.current_size() <= cache.max_size());
+    }
+
+    #[test]
+    fn get_many_mut_removes_too_expanded_entries() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        let result = cache.get_many_mut([&0, &1, &2], |[a, _, c]| {
+            a.resize(1024, 0);
+            c.resize(1024, 0);
+        });
+
+        assert!(matches!(result,
+            Err(MutateError::EntryTooLarge { key: 0, .. })));
+        assert_eq!(vec![1], cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn get_or_insert_with_only_calls_function_on_miss() {
+        let mut cache = large_test_cache();
+
+        assert_eq!(Ok(&"world"),
+            cache.get_or_insert_with("hello",
+                || panic!("function was called")));
+        assert_eq!(Ok(&"saturn"),
+            cache.get_or_insert_with("hey", || "saturn"));
+        assert_eq!(6, cache.len());
+        assert_eq!(Some((&"hey", &"saturn")), cache.peek_mru());
+    }
+
+    #[test]
+    fn get_or_insert_with_sets_most_recently_used_on_hit() {
+        let mut cache = large_test_cache();
+        cache.get_or_insert_with("hello", || "sun").unwrap();
+
+        assert_eq!(Some((&"hello", &"world")), cache.peek_mru());
+    }
+
+    #[test]
+    fn get_or_insert_with_rejects_too_large_entry() {
+        let mut cache = LruCache::new(256);
+        let result = cache.get_or_insert_with(0, || vec![0u8; 256]);
+
+        assert!(matches!(result, Err(InsertError::EntryTooLarge { .. })));
+        assert!(cache.is_empty());
+    }
 
-                let diff = new_value_size - old_value_size;
-                let new_entry_size = entry.size + diff;
+    #[derive(Debug, PartialEq)]
+    enum GetOrTryInsertTestError {
+        Computation,
+        Insert
+    }
 
-                if new_entry_size > max_size {
-                    // The entry is too large after the operation; eject it and
-                    // raise according error.
+    impl<K, V> From<InsertError<K, V>> for GetOrTryInsertTestError {
+        fn from(_: InsertError<K, V>) -> GetOrTryInsertTestError {
+            GetOrTryInsertTestError::Insert
+        }
+    }
 
-                    let old_entry_size = entry.size;
-                    let (key, value) = self.remove_entry(key).unwrap();
+    #[test]
+    fn get_or_try_insert_with_forwards_errors() {
+        let mut cache = LruCache::new(256);
 
-                    return Err(MutateError::EntryTooLarge {
-                        key,
-                        value,
-                        old_entry_size,
-                        new_entry_size,
-                        max_size
-                    });
-                }
+        assert_eq!(Err(GetOrTryInsertTestError::Computation),
+            cache.get_or_try_insert_with(0,
+                || Err(GetOrTryInsertTestError::Computation)));
+        assert_eq!(Err(GetOrTryInsertTestError::Insert),
+            cache.get_or_try_insert_with(0, || Ok(vec![0u8; 256])));
+        assert!(cache.is_empty());
+    }
 
-                entry.size = new_entry_size;
-                let entry_ptr = EntryPtr::new(entry as *mut Entry<K, V>);
-                self.current_size += diff;
-                self.touch_ptr(entry_ptr);
-                self.eject_to_target(max_size);
-            }
-            else {
-                // The operation was non-expanding; everything is ok.
+    #[test]
+    fn get_or_try_insert_with_inserts_on_miss() {
+        let mut cache = LruCache::new(1024);
+        let result: Result<_, GetOrTryInsertTestError> =
+            cache.get_or_try_insert_with(0, || Ok(vec![1u8]));
 
-                let diff = old_value_size - new_value_size;
-                entry.size -= diff;
-                let entry_ptr = EntryPtr::new(entry as *mut Entry<K, V>);
-                self.current_size -= diff;
-                self.touch_ptr(entry_ptr);
-            }
+        assert_eq!(Ok(&vec![1u8]), result);
+        assert_eq!(Some(&vec![1u8]), cache.peek(&0));
+    }
 
-            Ok(Some(result))
-        }
-        else {
-            Ok(None)
+    #[test]
+    fn pinned_entries_are_not_ejected() {
+        let mut cache = large_test_cache();
+        cache.pin("hello");
+        cache.pin("ahoy");
+        cache.set_max_size(cache.current_size());
+        cache.insert("hey", "mercury").unwrap();
+        cache.insert("bonjour", "saturn").unwrap();
+
+        assert_eq!(5, cache.len());
+        assert!(cache.contains("hello"));
+        assert!(!cache.contains("greetings"));
+        assert!(cache.contains("ahoy"));
+        assert!(!cache.contains("hi"));
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+    }
+
+    #[test]
+    fn remove_lru_skips_pinned_entries() {
+        let mut cache = large_test_cache();
+        cache.pin("hello");
+
+        assert_eq!(Some(("greetings", "moon")), cache.remove_lru());
+
+        cache.pin("ahoy");
+        cache.pin("hi");
+        cache.pin("good morning");
+
+        assert_eq!(None, cache.remove_lru());
+        assert_eq!(4, cache.len());
+    }
+
+    #[test]
+    fn remove_lru_returns_none_if_all_entries_are_pinned() {
+        let mut cache = large_test_cache();
+        let keys = cache.keys().copied().collect::<Vec<_>>();
+
+        for key in &keys {
+            cache.pin(key);
         }
+
+        let size = cache.current_size();
+
+        assert_eq!(None, cache.remove_lru());
+        assert_eq!(5, cache.len());
+        assert_eq!(size, cache.current_size());
+        assert_eq!(keys, cache.keys().copied().collect::<Vec<_>>());
     }
-}
 
-impl<K, V, S> IntoIterator for LruCache<K, V, S> {
-    type Item = (K, V);
-    type IntoIter = IntoIter<K, V, S>;
+    #[test]
+    fn pin_count_must_be_reverted_to_unpin() {
+        let mut cache = large_test_cache();
+        let entry_size = cache.current_size() / 5;
+
+        assert!(cache.pin("hello"));
+        assert!(cache.pin("hello"));
+        assert!(!cache.pin("bonjour"));
+        assert_eq!(entry_size, cache.pinned_size());
+        assert!(cache.unpin("hello"));
+        assert!(cache.is_pinned("hello"));
+        assert!(cache.unpin("hello"));
+        assert!(!cache.is_pinned("hello"));
+        assert!(!cache.unpin("hello"));
+        assert_eq!(0, cache.pinned_size());
+    }
 
-    fn into_iter(self) -> IntoIter<K, V, S> {
-        IntoIter::new(self)
+    #[test]
+    fn removing_pinned_entry_releases_pinned_size() {
+        let mut cache = large_test_cache();
+        cache.pin("hello");
+        cache.pin("greetings");
+        cache.remove("hello");
+        let entry_size = cache.current_size() / 4;
+
+        assert_eq!(entry_size, cache.pinned_size());
+
+        cache.clear();
+
+        assert_eq!(0, cache.pinned_size());
     }
-}
 
-impl<K, V, S> Clone for LruCache<K, V, S>
-where
-    K: Clone + Eq + Hash,
-    V: Clone,
-    S: BuildHasher + Clone
-{
-    fn clone(&self) -> LruCache<K, V, S> {
-        let max_size = self.max_size;
-        let capacity = self.capacity();
-        let hash_builder = self.hash_builder.clone();
-        let mut clone = LruCache::with_capacity_and_hasher(
-            max_size, capacity, hash_builder);
-        clone.current_size = self.current_size;
-        let mut next = self.seal.get().prev;
+    #[test]
+    fn insert_fails_if_pinned_entries_occupy_memory() {
+        let mut cache = large_test_cache();
+        cache.set_max_size(cache.current_size());
 
-        while next != self.seal {
-            let entry = unsafe { next.get().clone() };
-            next = entry.prev;
-            clone.insert_untracked(entry);
+        for key in ["hello", "greetings", "ahoy", "hi", "good morning"] {
+            cache.pin(key);
         }
 
-        clone
+        let result = cache.insert("hey", "mercury");
+
+        assert!(matches!(result, Err(InsertError::WouldEjectPinned {
+            available_memory: 0, ..
+        })));
+        assert_eq!(5, cache.len());
+
+        let result = cache.try_insert("hey", "mercury");
+
+        assert!(matches!(result, Err(TryInsertError::WouldEjectPinned { .. })));
+        assert_eq!(5, cache.len());
     }
-}
 
-impl<K, V, S> Drop for LruCache<K, V, S> {
-    fn drop(&mut self) {
-        for entry in self.table.drain() {
-            unsafe { entry.drop() };
+    #[test]
+    fn insert_may_replace_pinned_entry_if_pinned_entries_occupy_memory() {
+        let mut cache = large_test_cache();
+        cache.set_max_size(cache.current_size());
+
+        for key in ["hello", "greetings", "ahoy", "hi", "good morning"] {
+            cache.pin(key);
         }
 
-        unsafe {
-            self.seal.drop_seal();
+        assert_eq!(Ok(Some("world")), cache.insert("hello", "sun"));
+        assert!(cache.is_pinned("hello"));
+        assert_eq!(5, cache.len());
+        assert_eq!(cache.current_size(), cache.pinned_size());
+    }
+
+    #[test]
+    fn insert_keeps_pin_count_of_replaced_entry() {
+        let mut cache = large_test_cache();
+        cache.pin("hello");
+        cache.pin("hello");
+        cache.insert("hello", "sun").unwrap();
+
+        assert_eq!(entry_size(&"hello", &"sun"), cache.pinned_size());
+
+        cache.unpin("hello");
+
+        assert!(cache.is_pinned("hello"));
+
+        cache.unpin("hello");
+
+        assert!(!cache.is_pinned("hello"));
+        assert_eq!(0, cache.pinned_size());
+    }
+
+    #[test]
+    fn mutation_fails_if_it_does_not_fit_beside_pinned_entries() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.set_max_size(cache.current_size());
+        cache.pin(&0);
+        cache.pin(&1);
+        let pinned_size = cache.pinned_size();
+        let result = cache.mutate(&1, |v| v.push(0));
+
+        assert!(matches!(result, Err(MutateError::WouldEjectPinned { .. })));
+        assert_eq!(1, cache.len());
+        assert!(cache.pinned_size() < pinned_size);
+        assert_eq!(cache.current_size(), cache.pinned_size());
+    }
+
+    #[test]
+    fn mutation_of_pinned_entry_updates_pinned_size() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.pin(&0);
+        cache.mutate(&0, |v| {
+            v.resize(16, 0);
+            v.shrink_to_fit();
+        }).unwrap();
+
+        assert_eq!(cache.current_size(), cache.pinned_size());
+    }
+
+    #[test]
+    fn pins_survive_reallocation() {
+        let mut cache = large_test_cache();
+        cache.pin("hello");
+        cache.pin("ahoy");
+        cache.reserve(100);
+        cache.set_max_size(0);
+
+        assert_eq!(vec!["hello", "ahoy"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert!(cache.is_pinned("hello"));
+        assert!(cache.is_pinned("ahoy"));
+        assert_eq!(cache.current_size(), cache.pinned_size());
+    }
+
+    #[test]
+    fn metadata_is_only_allocated_once_entries_are_inserted() {
+        let mut cache = LruCache::new(1024);
+        cache.reserve(100);
+
+        assert!(cache.meta.is_empty());
+
+        cache.insert("hey", "mercury").unwrap();
+        cache.pin("hey");
+
+        assert!(!cache.meta.is_empty());
+        assert!(cache.is_pinned("hey"));
+    }
+
+    #[test]
+    fn reserving_adds_capacity() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        let additional = cache.capacity() - cache.len() + 5;
+        cache.reserve(additional);
+    
+        assert!(cache.capacity() >= additional + 2);
+        assert_eq!(2, cache.len());
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+        assert_eq!(Some((&"greetings", &"moon")), cache.peek_mru());
+    
+        let additional = additional + 10;
+    
+        assert!(cache.try_reserve(additional).is_ok());
+        assert!(cache.capacity() >= additional + 2);
+    }
+
+    #[test]
+    fn reserving_does_not_change_anything_when_capacity_is_not_exceeded() {
+        let mut cache = LruCache::with_capacity(1024, 5);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        let capacity_before = cache.capacity();
+
+        assert!(cache.try_reserve(3).is_ok());
+        assert_eq!(capacity_before, cache.capacity());
+    }
+    
+    #[test]
+    fn reserving_fails_on_internal_overflow() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+
+        let try_reserve_result = cache.try_reserve(usize::MAX);
+
+        assert_eq!(Err(TryReserveError::CapacityOverflow), try_reserve_result);
+    }
+
+    #[test]
+    fn reserving_fails_on_external_overflow() {
+        // In this test, the number of entries does not overflow usize, but the
+        // number of bytes does. Hence, lru-mem does not raise an error, but
+        // hashbrown does.
+
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        let additional = usize::MAX / mem::size_of::<Entry<&str, &str>>();
+
+        let try_reserve_result = cache.try_reserve(additional);
+
+        assert_eq!(Err(TryReserveError::CapacityOverflow), try_reserve_result);
+    }
+    
+    #[test]
+    fn shrinking_does_not_drop_below_len() {
+        let mut cache = large_test_cache();
+        cache.shrink_to(4);
+    
+        assert!(cache.capacity() >= 5);
+        assert_eq!(5, cache.len());
+    
+        cache.insert("hey", "mercury").unwrap();
+        cache.insert("what's up", "saturn").unwrap();
+        cache.shrink_to_fit();
+    
+        assert!(cache.capacity() >= 7);
+        assert_eq!(7, cache.len());
+    }
+
+    #[test]
+    fn shrinking_large_cache_decreases_capacity() {
+        let mut cache = LruCache::with_capacity(1024, 256);
+        cache.shrink_to(64);
+
+        assert!(cache.capacity() < 256);
+        assert!(cache.capacity() >= 64);
+
+        cache.insert("hey", "mercury").unwrap();
+        cache.insert("what's up", "saturn").unwrap();
+        cache.shrink_to_fit();
+
+        assert!(cache.capacity() < 64);
+        assert!(cache.capacity() >= 2);
+    }
+
+    #[test]
+    fn cache_created_with_capacity_does_not_reallocate_before_capacity_is_reached() {
+        let mut cache = LruCache::with_capacity(4096, 10);
+        let capacity_before = cache.capacity();
+
+        assert!(capacity_before >= 10);
+
+        for i in 0..10 {
+            cache.insert(i, "value").unwrap();
         }
+
+        assert_eq!(capacity_before, cache.capacity());
     }
-}
 
-impl<K: Debug, V: Debug, S> Debug for LruCache<K, V, S> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_map().entries(self.iter()).finish()
+    struct MockHasher {
+        hash_requests: Arc<Mutex<u32>>
     }
-}
 
-// Since the LruCache contains raw pointers, it is not automatically marked as
-// Send and Sync. We will provide manual implementations as well as arguments
-// why that is ok.
+    impl Hasher for MockHasher {
+        fn finish(&self) -> u64 {
+            *self.hash_requests.lock().unwrap() += 1;
+            0
+        }
 
-// It is implicitly assumed that every LruCache only contains pointers to
-// memory that belongs to the cache itself. So, if an LruCache is sent to
-// another thread, that memory can now only be accessed by that thread. In
-// other words, two LruCaches or anything related (e.g. iterators) can never
-// access the same memory. Therefore, sending them is no issue.
+        fn write(&mut self, _bytes: &[u8]) { }
+    }
+
+    struct MockBuildHasher {
+        hash_requests: Arc<Mutex<u32>>
+    }
+
+    impl BuildHasher for MockBuildHasher {
+        type Hasher = MockHasher;
+
+        fn build_hasher(&self) -> Self::Hasher {
+            MockHasher {
+                hash_requests: Arc::clone(&self.hash_requests)
+            }
+        }
+    }
+
+    #[test]
+    fn cache_uses_given_hasher() {
+        let build_hasher = MockBuildHasher {
+            hash_requests: Arc::new(Mutex::new(0))
+        };
+        let mut cache = LruCache::with_hasher(1024, build_hasher);
 
-unsafe impl<K: Send, V: Send, S: Send> Send for LruCache<K, V, S> { }
+        assert_eq!(0, *cache.hasher().hash_requests.lock().unwrap());
 
-// If an immutable reference to an LruCache exists, there is simultaneously no
-// mutable reference to the same cache. By design of the cache, any operations
-// applied to that reference will allow no writing access to any of its memory.
-// Those that yield any possibility of writing, such as cloning, are restricted
-// to newly allocated memory. Therefore, sending references is no issue, and by
-// definition of Sync, LruCache may implement it.
+        cache.insert("hello", "world").unwrap();
 
-unsafe impl<K: Sync, V: Sync, S: Sync> Sync for LruCache<K, V, S> { }
+        assert_eq!(1, *cache.hasher().hash_requests.lock().unwrap());
 
-#[cfg(test)]
-mod tests {
-    use std::hash::Hasher;
-    use std::sync::{Arc, Mutex};
-    use super::*;
+        cache.insert("greetings", "moon").unwrap();
+        cache.insert("ahoy", "mars").unwrap();
 
-    pub(crate) fn singleton_test_cache() -> LruCache<&'static str, &'static str> {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
-        cache
+        assert_eq!(3, *cache.hasher().hash_requests.lock().unwrap());
     }
 
-    pub(crate) fn large_test_cache() -> LruCache<&'static str, &'static str> {
-        let mut cache = LruCache::new(1024);
+    #[test]
+    fn cache_uses_given_hasher_and_weigher() {
+        let build_hasher = MockBuildHasher {
+            hash_requests: Arc::new(Mutex::new(0))
+        };
+        let mut cache = LruCache::with_capacity_hasher_and_weigher(10, 4,
+            build_hasher, |_: &&str, value: &&str| value.len());
+
+        assert!(cache.capacity() >= 4);
+
         cache.insert("hello", "world").unwrap();
         cache.insert("greetings", "moon").unwrap();
         cache.insert("ahoy", "mars").unwrap();
-        cache.insert("hi", "venus").unwrap();
-        cache.insert("good morning", "jupiter").unwrap();
-        cache
+
+        assert_eq!(3, *cache.hasher().hash_requests.lock().unwrap());
+        assert_eq!(vec![&"greetings", &"ahoy"],
+            cache.keys().collect::<Vec<_>>());
+        assert_eq!(8, cache.current_size());
     }
 
     #[test]
-    fn cache_correctly_inserts_with_sufficient_capacity() {
+    fn extending_inserts_in_order_and_skips_too_large_entries() {
         let mut cache = LruCache::new(1024);
-    
-        assert_eq!(0, cache.len());
-        assert_eq!(0, cache.current_size());
-        assert_eq!(1024, cache.max_size());
-    
-        assert_eq!(Ok(None),
-            cache.insert("key1".to_owned(), "value1".to_owned()));
-    
-        let new_size = cache.current_size();
-    
-        assert_eq!(1, cache.len());
-        assert!(new_size > 0);
-        assert_eq!(Some(&"value1".to_owned()), cache.get("key1"));
-    
-        assert_eq!(Ok(None),
-            cache.insert("key2".to_owned(), "value2".to_owned()));
-    
-        assert_eq!(2, cache.len());
-        assert!(cache.current_size() > new_size);
-        assert_eq!(Some(&"value1".to_owned()), cache.get("key1"));
-        assert_eq!(Some(&"value2".to_owned()), cache.get("key2"));
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.extend(vec![
+            (1, vec![0u8; 8]),
+            (2, vec![0u8; 1024]),
+            (3, vec![0u8; 8])
+        ]);
+
+        assert_eq!(vec![0, 1, 3], cache.keys().copied().collect::<Vec<_>>());
     }
-    
+
     #[test]
-    fn cache_deduplicates_keys() {
-        let mut cache = LruCache::new(1024);
-    
-        assert_eq!(Ok(None),
-            cache.insert("key".to_owned(), "value".to_owned()));
-    
-        let expected_size = cache.current_size;
-    
-        assert_eq!(Ok(Some("value".to_owned())),
-            cache.insert("key".to_owned(), "value".to_owned()));
-        assert_eq!(expected_size, cache.current_size());
-        assert_eq!(1, cache.len());
-    }
-    
-    fn string_with_size(size: usize) -> String {
-        let mut s = String::with_capacity(size);
-    
-        for _ in 0..size {
-            s.push('0');
-        }
-    
-        s
+    fn extending_ejects_lru_if_overflowing() {
+        let mut cache = large_test_cache();
+        cache.set_max_size(cache.current_size());
+        cache.extend(vec![("bonjour", "saturn"), ("hey", "pluto")]);
+
+        assert!(!cache.contains("hello"));
+        assert!(!cache.contains("greetings"));
+        assert_eq!(Some((&"hey", &"pluto")), cache.peek_mru());
     }
-    
+
     #[test]
-    fn cache_ejects_lru_if_overflowing() {
-        let mut cache = LruCache::new(2048);
-    
-        // On 256-bit arch, each entry requires an extra 289 bytes per entry
-        // in addition to the size of the value in bytes (see test case
-        // "entry_correctly_computes_size", value_str_bytes = 1).
-        // On 16-bit arch, this would be 19 bytes.
-        // The numbers in this test case accommodate anywhere from 15 to 696
-        // extra bytes (676 * 3 + 15 = 2050, 676 * 2 + 696 = 2048).
-        
-        cache.insert("a".to_owned(), string_with_size(676)).unwrap();
-        cache.insert("b".to_owned(), string_with_size(676)).unwrap();
-        
-        let expected_size = cache.current_size();
-        
-        cache.insert("c".to_owned(), string_with_size(676)).unwrap();
-        
-        assert_eq!(expected_size, cache.current_size());
-        assert_eq!(2, cache.len());
-        assert!(cache.peek("a").is_none());
-        assert!(cache.peek("b").is_some());
-        assert!(cache.peek("c").is_some());
-        
-        assert_eq!(Some("b"), cache.peek_lru().map(|(key, _)| key.as_str()));
-        assert_eq!(Some("c"), cache.peek_mru().map(|(key, _)| key.as_str()));
+    fn from_iter_with_max_size_reserves_only_fitting_capacity() {
+        let max_size = 4 * entry_size(&0u64, &0u64);
+        let (cache, rejected) =
+            LruCache::from_iter_with_max_size((0..1000u64).map(|i| (i, i)),
+                max_size);
+
+        assert!(rejected.is_empty());
+        assert_eq!(4, cache.len());
+        assert!(cache.capacity() < 1000);
+        assert_eq!(vec![996, 997, 998, 999],
+            cache.keys().copied().collect::<Vec<_>>());
     }
-    
+
     #[test]
-    fn getting_sets_most_recently_used() {
-        let mut cache = LruCache::new(2048);
-    
-        // See the argument why the sizes are like this in test case
-        // "cache_ejects_lru_if_overflowing".
-    
-        cache.insert("a".to_owned(), string_with_size(674)).unwrap();
-        cache.insert("b".to_owned(), string_with_size(674)).unwrap();
-        cache.get(&"a".to_owned());
-        cache.insert("c".to_owned(), string_with_size(674)).unwrap();
-    
-        assert!(cache.get("a").is_some());
-        assert!(cache.get("b").is_none());
-        assert!(cache.get("c").is_some());
-    
-        assert_eq!("a", unsafe { cache.seal.get().prev.get().key() });
-        assert_eq!("c", unsafe { cache.seal.get().next.get().key() });
-    
-        cache.get("a");
-    
-        assert_eq!("c", unsafe { cache.seal.get().prev.get().key() });
-        assert_eq!("a", unsafe { cache.seal.get().next.get().key() });
+    fn extending_with_custom_weigher_reserves_by_entry_count() {
+        let mut cache = LruCache::with_weigher(2, |_: &u64, _: &u64| 1);
+        cache.extend((0..1000u64).map(|i| (i, i)));
+
+        assert_eq!(2, cache.len());
+        assert!(cache.capacity() < 1000);
+        assert_eq!(vec![998, 999], cache.keys().copied().collect::<Vec<_>>());
     }
 
     #[test]
-    fn empty_cache_has_no_lru_and_mru() {
-        let cache = LruCache::<&str, &str>::new(1024);
+    fn extending_with_custom_weigher_keeps_all_fitting_entries() {
+        let mut cache = LruCache::with_weigher(1000, |_: &u64, _: &u64| 1);
+        cache.extend((0..1000u64).map(|i| (i, i)));
 
-        assert!(cache.peek_lru().is_none());
-        assert!(cache.peek_mru().is_none());
+        assert_eq!(1000, cache.len());
+        assert!(cache.capacity() >= 1000);
+        assert!((0..1000u64).all(|i| cache.contains(&i)));
     }
 
     #[test]
-    fn get_lru_sets_most_recently_used() {
-        let mut cache = LruCache::new(2048);
+    fn from_iter_with_max_size_reports_too_large_entries() {
+        let (cache, rejected) = LruCache::from_iter_with_max_size(vec![
+            (0, vec![0u8; 8]),
+            (1, vec![0u8; 1024]),
+            (2, vec![0u8; 2048])
+        ], 1024);
 
-        cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-
-        let lru = cache.get_lru();
+        assert_eq!(1, cache.len());
+        assert!(matches!(rejected.as_slice(), [
+            InsertError::EntryTooLarge { key: 1, .. },
+            InsertError::EntryTooLarge { key: 2, .. }
+        ]));
+    }
 
-        assert_eq!(Some((&"hello", &"world")), lru);
+    #[test]
+    fn split_off_lru_moves_entries_totalling_at_least_bytes() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+        cache.insert(2, vec![0u8; 8]).unwrap();
+        let size_before = cache.current_size();
+        let entry_size = size_before / 3;
+        let other = cache.split_off_lru(entry_size + 1);
+
+        assert_eq!(vec![0, 1], other.keys().copied().collect::<Vec<_>>());
+        assert_eq!(vec![2], cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(2 * entry_size, other.current_size());
+        assert_eq!(entry_size, cache.current_size());
+        assert_eq!(cache.max_size(), other.max_size());
+    }
 
-        cache.set_max_size(cache.current_size() - 1);
+    #[test]
+    fn split_off_lru_moves_all_entries_if_too_few_bytes() {
+        let mut cache = large_test_cache();
+        let size_before = cache.current_size();
+        let other = cache.split_off_lru(usize::MAX);
 
-        assert!(cache.contains("hello"));
-        assert!(!cache.contains("greetings"));
+        assert!(cache.is_empty());
+        assert_eq!(0, cache.current_size());
+        assert_eq!(5, other.len());
+        assert_eq!(size_before, other.current_size());
+        assert_eq!(Some(&"world"), other.peek("hello"));
     }
-    
+
     #[test]
-    fn peeking_does_not_set_most_recently_used() {
-        let mut cache = LruCache::new(2048);
-    
-        cache.insert("a".to_owned(), string_with_size(674)).unwrap();
-        cache.insert("b".to_owned(), string_with_size(674)).unwrap();
-        cache.peek(&"a".to_owned());
-        cache.insert("c".to_owned(), string_with_size(674)).unwrap();
-    
-        assert!(cache.get("a").is_none());
-        assert!(cache.get("b").is_some());
-        assert!(cache.get("c").is_some());
+    fn split_off_at_preserves_pins_and_order() {
+        let mut cache = large_test_cache();
+        cache.pin("greetings");
+        let mut other = cache.split_off_at(3);
 
-        cache.peek_entry(&"b".to_owned());
-        cache.insert("d".to_owned(), string_with_size(674)).unwrap();
+        assert_eq!(vec!["hello", "greetings", "ahoy"],
+            other.keys().copied().collect::<Vec<_>>());
+        assert_eq!(vec!["hi", "good morning"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(0, cache.pinned_size());
+        assert!(other.is_pinned("greetings"));
+        assert!(other.pinned_size() > 0);
 
-        assert!(cache.get("b").is_none());
-        assert!(cache.get("c").is_some());
-        assert!(cache.get("d").is_some());
-    }
-    
-    #[test]
-    fn cache_rejects_too_large_entry() {
-        let mut cache = LruCache::new(256);
-        let key = "This is a pretty long key, especially considering that \
-            keys should normally be rather small to avoid long hashing times."
-            .to_owned();
-        let value = "Although the key alone has insufficient size, together \
-            with this string it pushes pushes the total memory requirement of the \
-            entry over the capacity of the cache.".to_owned();
-    
-        assert!(matches!(cache.insert(key, value),
-            Err(InsertError::EntryTooLarge { .. })));
+        other.insert("bonjour", "saturn").unwrap();
+
+        assert_eq!(Some((&"bonjour", &"saturn")), other.peek_mru());
+        assert!(cache.split_off_at(0).is_empty());
     }
-    
+
     #[test]
-    fn precisely_fitting_entry_does_not_eject_lru() {
+    fn merge_interleaves_usage_histories() {
         let mut cache = LruCache::new(1024);
-        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
-        cache.insert("greetings".to_owned(), "moon".to_owned()).unwrap();
-    
-        let key = "ahoy".to_owned();
-        let mut value = "mars".to_owned();
-        value.shrink_to_fit();
-        let required_size = cache.max_size() - cache.current_size();
-        let additional_bytes = required_size - entry_size(&key, &value);
-        let additional_data = vec![b's'; additional_bytes];
-        value.push_str(&String::from_utf8(additional_data).unwrap());
-        value.shrink_to_fit();
-    
-        assert_eq!(required_size, entry_size(&key, &value));
-    
-        cache.insert(key, value).unwrap();
-    
-        assert_eq!(3, cache.len());
+        let mut other = LruCache::new(1024);
+        cache.insert(0, 0).unwrap();
+        cache.insert(3, 3).unwrap();
+        cache.touch(&0);
+        other.insert(1, 1).unwrap();
+        other.insert(2, 2).unwrap();
+        other.insert(4, 4).unwrap();
+        cache.merge(other, ConflictPolicy::KeepExisting);
+
+        assert_eq!(vec![1, 3, 2, 0, 4],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(5 * entry_size(&0, &0), cache.current_size());
     }
 
     #[test]
-    fn auto_reallocation_doubles_capacity() {
-        let mut cache = LruCache::with_capacity(4096, 10);
-        let capacity = cache.capacity();
+    fn merge_resolves_conflicts_according_to_policy() {
+        let build = || {
+            let mut cache = LruCache::new(1024);
+            let mut other = LruCache::new(1024);
+            cache.insert("hello", "world").unwrap();
+            cache.insert("greetings", "moon").unwrap();
+            other.insert("ahoy", "mars").unwrap();
+            other.insert("hello", "venus").unwrap();
+            (cache, other)
+        };
 
-        for index in 0..capacity {
-            cache.insert(index, 0).unwrap();
-        }
+        let (mut cache, other) = build();
+        cache.merge(other, ConflictPolicy::KeepExisting);
 
-        cache.insert(capacity, 0).unwrap();
+        assert_eq!(Some(&"world"), cache.peek("hello"));
 
-        assert_eq!(2 * capacity, cache.capacity());
+        let (mut cache, other) = build();
+        cache.merge(other, ConflictPolicy::KeepIncoming);
+
+        assert_eq!(Some(&"venus"), cache.peek("hello"));
+
+        let (mut cache, other) = build();
+        cache.merge(other, ConflictPolicy::KeepMoreRecent);
+
+        assert_eq!(Some(&"venus"), cache.peek("hello"));
+        assert_eq!(vec!["ahoy", "greetings", "hello"],
+            cache.keys().copied().collect::<Vec<_>>());
+
+        let (mut cache, other) = build();
+        cache.touch("hello");
+        cache.merge(other, ConflictPolicy::KeepMoreRecent);
+
+        assert_eq!(Some(&"world"), cache.peek("hello"));
+        assert_eq!(vec!["ahoy", "greetings", "hello"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(3, cache.len());
     }
-    
+
     #[test]
-    fn try_insert_works_as_insert_if_ok() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
-        cache.try_insert("greetings".to_owned(), "moon".to_owned()).unwrap();
-    
-        assert_eq!(2, cache.len());
-        assert_eq!("world".to_owned(), cache.remove_lru().unwrap().1);
-        assert_eq!("moon".to_owned(), cache.remove_lru().unwrap().1);
+    fn merge_ejects_lru_if_overflowing() {
+        let mut cache = large_test_cache();
+        let mut other = LruCache::new(1024);
+        other.insert("bonjour", "saturn").unwrap();
+        cache.set_max_size(cache.current_size());
+        cache.merge(other, ConflictPolicy::KeepExisting);
+
+        assert_eq!(5, cache.len());
+        assert!(!cache.contains("hello"));
+        assert!(cache.contains("bonjour"));
     }
-    
+
     #[test]
-    fn try_insert_fails_on_duplication() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
-        let result = cache.try_insert("hello".to_owned(), "moon".to_owned());
-    
-        assert!(matches!(result, Err(TryInsertError::OccupiedEntry { .. })));
-        assert_eq!(1, cache.len());
-        assert_eq!(&"world".to_owned(), cache.get("hello").unwrap());
+    fn merge_places_entries_by_when_they_were_last_used() {
+        let mut cache = large_test_cache();
+        let mut other = LruCache::new(1024);
+        cache.touch("hello");
+        other.insert("bonjour", "saturn").unwrap();
+        other.insert("salut", "jupiter").unwrap();
+
+        for _ in 0..10 {
+            other.touch("salut");
+        }
+
+        cache.merge(other, ConflictPolicy::KeepExisting);
+
+        assert_eq!(vec!["bonjour", "greetings", "ahoy", "hi", "good morning",
+            "hello", "salut"], cache.keys().copied().collect::<Vec<_>>());
     }
-    
+
     #[test]
-    fn try_insert_fails_if_eject_required() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
-        cache.insert("greetings".to_owned(), "moon".to_owned()).unwrap();
-        cache.set_max_size(cache.current_size());
-        let result = cache.try_insert("ahoy".to_owned(), "mars".to_owned());
-    
-        assert!(matches!(result, Err(TryInsertError::WouldEjectLru { .. })));
-        assert_eq!(2, cache.len());
-        assert!(!cache.contains("ahoy"));
+    fn merged_demoted_entries_count_as_used_with_previous_lru() {
+        let mut cache = large_test_cache();
+        let mut other = LruCache::new(1024);
+        other.insert("bonjour", "saturn").unwrap();
+        other.insert_cold("salut", "jupiter").unwrap();
+        cache.demote("hi");
+        cache.merge(other, ConflictPolicy::KeepExisting);
+
+        assert_eq!(vec!["hi", "hello", "salut", "bonjour", "greetings", "ahoy",
+            "good morning"], cache.keys().copied().collect::<Vec<_>>());
     }
-    
+
     #[test]
-    fn try_insert_fails_if_too_large() {
+    fn merge_continues_counting_accesses_from_greater_count() {
         let mut cache = LruCache::new(1024);
-        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
-        let value = String::from_utf8(vec![b'0'; 1024]).unwrap();
-        let result = cache.try_insert("key".to_owned(), value);
-    
-        assert!(matches!(result, Err(TryInsertError::EntryTooLarge { .. })));
-        assert_eq!(1, cache.len());
-        assert!(!cache.contains("key"));
+        let mut other = LruCache::new(1024);
+        let mut third = LruCache::new(1024);
+        cache.insert("hello", "world").unwrap();
+        cache.insert("greetings", "moon").unwrap();
+        other.insert("ahoy", "mars").unwrap();
+
+        for _ in 0..10 {
+            other.touch("ahoy");
+            third.insert("hi", "venus").unwrap();
+        }
+
+        cache.merge(other, ConflictPolicy::KeepExisting);
+        cache.touch("hello");
+        cache.merge(third, ConflictPolicy::KeepExisting);
+
+        assert_eq!(vec!["greetings", "hi", "ahoy", "hello"],
+            cache.keys().copied().collect::<Vec<_>>());
     }
-    
+
     #[test]
-    fn removing_works() {
+    fn merge_keeps_least_recently_used_entry_idle_the_longest() {
         let mut cache = LruCache::new(1024);
+        let mut other = LruCache::new(1024);
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(10 * SECOND));
         cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-        cache.insert("ahoy", "mars").unwrap();
-    
-        assert_eq!(Some(("hello", "world")), cache.remove_entry("hello"));
-        assert_eq!(None, cache.remove("hello"));
+        clock.advance(5 * SECOND);
+        other.insert("greetings", "moon").unwrap();
+        other.insert("ahoy", "mars").unwrap();
+        cache.merge(other, ConflictPolicy::KeepExisting);
+        clock.advance(5 * SECOND);
+        cache.remove_idle();
+
+        assert_eq!(vec!["greetings", "ahoy"],
+            cache.keys().copied().collect::<Vec<_>>());
+
+        clock.advance(5 * SECOND);
+        cache.remove_idle();
+
+        assert!(cache.is_empty());
     }
 
     #[test]
-    fn removing_mru_works() {
+    fn clone_creates_independent_cache() {
         let mut cache = LruCache::new(1024);
         cache.insert("hello", "world").unwrap();
         cache.insert("greetings", "moon").unwrap();
-        cache.insert("ahoy", "mars").unwrap();
-        cache.remove_mru();
+    
+        let mut clone = cache.clone();
+        clone.insert("ahoy", "mars").unwrap();
 
-        assert_eq!(2, cache.len());
-        assert!(cache.contains("hello"));
-        assert!(cache.contains("greetings"));
+        cache.remove(&"hello");
+        cache.touch(&"greetings");
+    
+        assert_eq!(1, cache.len());
+        assert_eq!(None, cache.get(&"hello"));
+        assert_eq!(Some(&"moon"), cache.get(&"greetings"));
+        assert_eq!(None, cache.get(&"ahoy"));
+    
+        assert_eq!(3, clone.len());
+        assert_eq!(Some(&"world"), clone.get(&"hello"));
+        assert_eq!(Some(&"moon"), clone.get(&"greetings"));
+        assert_eq!(Some(&"mars"), clone.get(&"ahoy"));
+    
+        assert!(clone.current_size() > cache.current_size());
+
+        let cache_drained = cache.drain().collect::<Vec<_>>();
+        let cache_drained_expected = vec![("greetings", "moon")];
+        let clone_drained = clone.drain().collect::<Vec<_>>();
+        let clone_drained_expected = vec![
+            ("hello", "world"),
+            ("greetings", "moon"),
+            ("ahoy", "mars")
+        ];
+
+        assert_eq!(cache_drained_expected, cache_drained);
+        assert_eq!(clone_drained_expected, clone_drained);
+    }
+    
+    type RemovalLog = Arc<Mutex<Vec<(&'static str, RemovalCause)>>>;
+
+    pub(crate) fn register_removal_log(
+            cache: &mut LruCache<&'static str, &'static str>) -> RemovalLog {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let log_clone = Arc::clone(&log);
+        cache.set_removal_listener(move |key, _, cause| {
+            log_clone.lock().unwrap().push((key, cause));
+        });
+        log
     }
 
     #[test]
-    fn clearing_works() {
+    fn removal_listener_reports_ejection_but_not_returned_values() {
         let mut cache = large_test_cache();
-        cache.clear();
+        let log = register_removal_log(&mut cache);
+        cache.set_max_size(cache.current_size());
 
-        assert!(cache.is_empty());
-        assert!(cache.peek_lru().is_none());
-        assert!(cache.peek_mru().is_none());
-        assert!(cache.iter().next().is_none());
+        assert_eq!(Some("moon"), cache.insert("greetings", "sun").unwrap());
+
+        cache.insert("hey", "mercury").unwrap();
+
+        assert_eq!(vec![("hello", RemovalCause::Ejected)],
+            *log.lock().unwrap());
     }
-    
+
     #[test]
-    fn retain_does_not_affect_empty_cache() {
-        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
-        cache.retain(|k, v| v.len() as u64 == *k);
-    
-        assert_eq!(0, cache.len());
+    fn removal_listener_takes_ownership_of_dropped_entries() {
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let dropped_clone = Arc::clone(&dropped);
+        let mut cache = LruCache::new(1024);
+        cache.set_removal_listener(move |key: String, value: String, _| {
+            dropped_clone.lock().unwrap().push((key, value));
+        });
+        cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+        cache.clear();
+
+        assert_eq!(vec![("apple".to_owned(), "sweet".to_owned())],
+            *dropped.lock().unwrap());
     }
-    
+
     #[test]
-    fn retain_works_if_all_match() {
-        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
-        cache.insert(1, vec![0]).unwrap();
-        cache.insert(5, vec![2, 3, 5, 7, 11]).unwrap();
-        cache.insert(4, vec![1, 4, 9, 16]).unwrap();
-        cache.retain(|k, v| v.len() as u64 == *k);
-    
-        assert_eq!(3, cache.len());
-        assert!(cache.contains(&1));
-        assert!(cache.contains(&4));
-        assert!(cache.contains(&5));
+    fn removal_listener_reports_max_size_reduction() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        cache.set_max_size(cache.current_size() - 1);
+
+        assert_eq!(vec![("hello", RemovalCause::MaxSizeReduced)],
+            *log.lock().unwrap());
     }
-    
+
     #[test]
-    fn retain_works_if_none_match() {
-        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
-        cache.insert(1, vec![0, 1]).unwrap();
-        cache.insert(5, vec![2, 3, 5, 7, 11, 13]).unwrap();
-        cache.insert(4, vec![1, 4, 9, 16, 25]).unwrap();
-        cache.retain(|k, v| v.len() as u64 == *k);
-    
-        assert_eq!(0, cache.len());
-        assert!(!cache.contains(&1));
-        assert!(!cache.contains(&4));
-        assert!(!cache.contains(&5));
+    fn removal_listener_does_not_report_explicit_removal() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        cache.remove("ahoy");
+        cache.remove_lru();
+        cache.remove_mru();
+
+        assert_eq!(2, cache.drain().count());
+        assert!(log.lock().unwrap().is_empty());
     }
-    
+
     #[test]
-    fn retain_works_if_some_match() {
-        let mut cache: LruCache<u64, Vec<u8>> = LruCache::new(1024);
-        cache.insert(1, vec![0, 1]).unwrap();
-        cache.insert(5, vec![2, 3, 5, 7, 11]).unwrap();
-        cache.insert(4, vec![1, 4, 9, 16]).unwrap();
-        cache.retain(|k, v| v.len() as u64 == *k);
-    
-        assert_eq!(2, cache.len());
-        assert!(!cache.contains(&1));
-        assert!(cache.contains(&4));
-        assert!(cache.contains(&5));
+    fn removal_listener_reports_entries_dropped_by_iterators() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        let max_size = cache.current_size() - 1;
+        cache.resize(max_size);
+        cache.drain().next();
+
+        assert_eq!(vec![
+            ("hello", RemovalCause::MaxSizeReduced),
+            ("ahoy", RemovalCause::Cleared),
+            ("hi", RemovalCause::Cleared),
+            ("good morning", RemovalCause::Cleared)
+        ], *log.lock().unwrap());
     }
 
     #[test]
-    fn retain_sets_lru_and_mru_if_necessary() {
-        let mut cache = LruCache::new(1024);
-        cache.insert(1, 0).unwrap();
-        cache.insert(2, 0).unwrap();
-        cache.insert(3, 0).unwrap();
-        cache.insert(4, 0).unwrap();
-        cache.retain(|&k, _| k != 1 && k != 4);
+    fn removal_listener_reports_clear_and_retain_in_lru_order() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        cache.retain(|&k, _| k != "ahoy" && k != "hello");
+        cache.clear();
 
-        assert_eq!(Some((&2, &0)), cache.peek_lru());
-        assert_eq!(Some((&3, &0)), cache.peek_mru());
+        assert_eq!(vec![
+            ("hello", RemovalCause::Filtered),
+            ("ahoy", RemovalCause::Filtered),
+            ("greetings", RemovalCause::Cleared),
+            ("hi", RemovalCause::Cleared),
+            ("good morning", RemovalCause::Cleared)
+        ], *log.lock().unwrap());
     }
-    
+
     #[test]
-    fn contains_works() {
+    fn removal_listener_reports_too_expanding_mutation_not_returned() {
+        let causes = Arc::new(Mutex::new(Vec::new()));
+        let causes_clone = Arc::clone(&causes);
         let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
-    
-        assert!(cache.contains("hello"));
-        assert!(!cache.contains("greetings"));
+        cache.set_removal_listener(move |_: u32, _: Vec<u8>, cause| {
+            causes_clone.lock().unwrap().push(cause);
+        });
+        cache.insert(0, vec![0u8; 32]).unwrap();
+        cache.insert(1, vec![0u8; 32]).unwrap();
+
+        assert!(cache.mutate(&0, |v| v.resize(1024, 0)).is_err());
+        assert!(causes.lock().unwrap().is_empty());
+
+        cache.get_mut(&1).unwrap().resize(1024, 0);
+
+        assert_eq!(vec![RemovalCause::EntryTooLarge], *causes.lock().unwrap());
     }
-    
+
     #[test]
-    fn increasing_max_size_keeps_all_elements() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-        cache.set_max_size(2048);
-    
-        assert_eq!(2, cache.len());
-        assert_eq!(2048, cache.max_size());
+    fn cleared_removal_listener_is_not_called() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        cache.clear_removal_listener();
+        cache.clear();
+
+        assert!(log.lock().unwrap().is_empty());
     }
-    
+
+    #[derive(Clone)]
+    struct ManualClock {
+        now: Arc<Mutex<Instant>>
+    }
+
+    impl ManualClock {
+        fn advance(&self, duration: Duration) {
+            *self.now.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    fn set_manual_clock<K, V, S, W>(cache: &mut LruCache<K, V, S, W>)
+            -> ManualClock {
+        let clock = ManualClock {
+            now: Arc::new(Mutex::new(Instant::now()))
+        };
+        cache.set_clock(clock.clone());
+        clock
+    }
+
+    const SECOND: Duration = Duration::from_secs(1);
+
     #[test]
-    fn decreasing_max_size_below_current_size_drops_elements() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-        cache.set_max_size(cache.current_size() - 1);
-    
-        assert_eq!(1, cache.len());
-        assert!(cache.current_size() < cache.max_size());
-        assert!(cache.max_size() < 1024);
+    fn iter_from_expired_entry_returns_none() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        clock.advance(SECOND);
+
+        assert!(cache.iter_from("hey").is_none());
+        assert!(cache.iter_from_back("hey").is_none());
     }
-    
+
     #[test]
-    fn cache_correctly_applies_mutation() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
-        cache.insert("greetings".to_owned(), "moon".to_owned()).unwrap();
-    
-        let old_size = cache.current_size();
-        let result = cache.mutate("greetings", |s| {
-            s.push_str(", from 384400 km away");
-            s.shrink_to_fit();
-            s.len()
-        });
-    
-        assert_eq!(Ok(Some(25)), result);
-        assert_eq!(2, cache.len());
-        assert_eq!(old_size + 21, cache.current_size());
-        assert_eq!(Some(&"moon, from 384400 km away".to_owned()),
-            cache.get("greetings"));
+    fn entry_with_ttl_is_present_until_expired() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", 2 * SECOND).unwrap();
+        clock.advance(SECOND);
+
+        assert_eq!(Some(&"mercury"), cache.peek("hey"));
+        assert_eq!(Some(&"mercury"), cache.get("hey"));
+
+        clock.advance(SECOND);
+
+        assert_eq!(None, cache.peek("hey"));
+        assert!(!cache.contains("hey"));
+        assert_eq!(None, cache.get("hey"));
     }
-    
+
     #[test]
-    fn cache_rejects_too_expanding_mutation() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", vec![0u8; 32]).unwrap();
-        cache.insert("greetings", vec![0u8; 32]).unwrap();
-    
-        let old_size = cache.current_size();
-        let result = cache.mutate("hello", |v| {
-            v.append(&mut vec![0u8; 1000]);
-        });
-    
-        assert!(matches!(result, Err(MutateError::EntryTooLarge { .. })));
-        assert_eq!(1, cache.len());
-        assert!(cache.current_size() < old_size);
-        assert_eq!(None, cache.get("hello"));
+    fn getting_expired_entry_reclaims_memory() {
+        let mut cache = large_test_cache();
+        let size_before = cache.current_size();
+        let log = register_removal_log(&mut cache);
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        clock.advance(SECOND);
+
+        assert!(!cache.contains("hey"));
+        assert_eq!(6, cache.len());
+
+        cache.get("hey");
+
+        assert_eq!(5, cache.len());
+        assert_eq!(size_before, cache.current_size());
+        assert_eq!(vec![("hey", RemovalCause::Expired)], *log.lock().unwrap());
     }
 
     #[test]
-    fn non_expanding_mutation_works() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", vec![0u8; 32]).unwrap();
-        cache.insert("greetings", vec![0u8; 32]).unwrap();
+    fn default_ttl_applies_to_inserted_entries_only() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_default_ttl(Some(SECOND));
+        cache.insert("hey", "mercury").unwrap();
+        cache.try_insert("howdy", "saturn").unwrap();
+        cache.entry("yo").or_insert("uranus").unwrap();
+        clock.advance(SECOND);
 
-        let old_size = cache.current_size();
-        let result = cache.mutate("hello", |v| {
-            *v = Vec::new();
-        });
+        assert!(!cache.contains("hey"));
+        assert!(!cache.contains("howdy"));
+        assert!(!cache.contains("yo"));
+        assert!(cache.contains("hello"));
+    }
 
-        assert!(matches!(result, Ok(Some(_))));
-        assert!(cache.current_size() < old_size);
-        assert_eq!(2, cache.len());
+    #[test]
+    fn purge_expired_removes_only_expired_entries() {
+        let mut cache = large_test_cache();
+        let size_before = cache.current_size();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        cache.insert_with_ttl("howdy", "saturn", 2 * SECOND).unwrap();
+        cache.insert_with_ttl("yo", "uranus", SECOND).unwrap();
+        clock.advance(SECOND);
+        cache.purge_expired();
+
+        assert_eq!(6, cache.len());
+        assert!(cache.contains("howdy"));
+        assert!(size_before < cache.current_size());
+
+        clock.advance(SECOND);
+        cache.purge_expired();
+
+        assert_eq!(5, cache.len());
+        assert_eq!(size_before, cache.current_size());
     }
 
     #[test]
-    fn mutation_on_non_existent_element_is_never_called() {
-        let mut cache = LruCache::<&str, &str>::new(1024);
-        let result = cache.mutate("hello", |_| { panic!("mutation was called") });
+    fn pinned_entries_do_not_expire() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        cache.pin("hey");
+        clock.advance(SECOND);
+        cache.purge_expired();
 
-        assert_eq!(Ok(None), result);
+        assert_eq!(Some(&"mercury"), cache.get("hey"));
+
+        cache.unpin("hey");
+
+        assert_eq!(None, cache.get("hey"));
     }
-    
+
+    /// Creates a large test cache with an expired entry between "ahoy" and
+    /// "hi".
+    pub(crate) fn large_test_cache_with_expired_entry()
+            -> LruCache<&'static str, &'static str> {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        cache.touch("hi");
+        cache.touch("good morning");
+        clock.advance(SECOND);
+        cache
+    }
+
     #[test]
-    fn reserving_adds_capacity() {
+    fn get_lru_removes_expired_entries_instead_of_touching_them() {
         let mut cache = LruCache::new(1024);
+        let log = register_removal_log(&mut cache);
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
         cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-        let additional = cache.capacity() - cache.len() + 5;
-        cache.reserve(additional);
-    
-        assert!(cache.capacity() >= additional + 2);
-        assert_eq!(2, cache.len());
-        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
-        assert_eq!(Some((&"greetings", &"moon")), cache.peek_mru());
-    
-        let additional = additional + 10;
-    
-        assert!(cache.try_reserve(additional).is_ok());
-        assert!(cache.capacity() >= additional + 2);
+        clock.advance(SECOND);
+
+        assert_eq!(Some((&"hello", &"world")), cache.get_lru());
+        assert_eq!(1, cache.len());
+        assert_eq!(entry_size(&"hello", &"world"), cache.current_size());
+        assert_eq!(vec![("hey", RemovalCause::Expired)], *log.lock().unwrap());
     }
 
     #[test]
-    fn reserving_does_not_change_anything_when_capacity_is_not_exceeded() {
-        let mut cache = LruCache::with_capacity(1024, 5);
+    fn get_lru_does_not_revive_idle_entry() {
+        let mut cache = LruCache::new(1024);
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(SECOND));
         cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-        let capacity_before = cache.capacity();
+        clock.advance(SECOND);
 
-        assert!(cache.try_reserve(3).is_ok());
-        assert_eq!(capacity_before, cache.capacity());
+        assert_eq!(None, cache.get_lru());
+        assert!(cache.is_empty());
+        assert_eq!(0, cache.current_size());
     }
-    
+
     #[test]
-    fn reserving_fails_on_internal_overflow() {
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
+    fn peek_lru_and_peek_mru_skip_expired_entries() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        cache.demote("hey");
+        cache.insert_with_ttl("yo", "uranus", SECOND).unwrap();
+        clock.advance(SECOND);
 
-        let try_reserve_result = cache.try_reserve(usize::MAX);
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+        assert_eq!(Some((&"good morning", &"jupiter")), cache.peek_mru());
+        assert_eq!(7, cache.len());
+    }
 
-        assert_eq!(Err(TryReserveError::CapacityOverflow), try_reserve_result);
+    #[test]
+    fn nth_lru_and_nth_mru_skip_expired_entries() {
+        let cache = large_test_cache_with_expired_entry();
+
+        assert_eq!(Some((&"hi", &"venus")), cache.nth_lru(3));
+        assert_eq!(Some((&"good morning", &"jupiter")), cache.nth_lru(4));
+        assert_eq!(None, cache.nth_lru(5));
+        assert_eq!(Some((&"ahoy", &"mars")), cache.nth_mru(2));
+        assert_eq!(Some((&"hello", &"world")), cache.nth_mru(4));
+        assert_eq!(None, cache.nth_mru(5));
     }
 
     #[test]
-    fn reserving_fails_on_external_overflow() {
-        // In this test, the number of entries does not overflow usize, but the
-        // number of bytes does. Hence, lru-mem does not raise an error, but
-        // hashbrown does.
+    fn pins_alone_do_not_require_expiry_checks() {
+        let mut cache = large_test_cache();
+        cache.pin("ahoy");
 
-        let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
-        let additional = usize::MAX / mem::size_of::<Entry<&str, &str>>();
+        assert!(!cache.may_expire());
+        assert!(cache.expiry_check().is_none());
+        assert_eq!(Some((&"ahoy", &"mars")), cache.nth_lru(2));
+        assert_eq!(Some((&"hi", &"venus")), cache.nth_mru(1));
 
-        let try_reserve_result = cache.try_reserve(additional);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
 
-        assert_eq!(Err(TryReserveError::CapacityOverflow), try_reserve_result);
+        assert!(cache.may_expire());
     }
-    
+
     #[test]
-    fn shrinking_does_not_drop_below_len() {
+    fn len_and_current_size_count_expired_entries_until_removed() {
         let mut cache = large_test_cache();
-        cache.shrink_to(4);
-    
-        assert!(cache.capacity() >= 5);
+        let size = cache.current_size();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        clock.advance(SECOND);
+
+        assert_eq!(6, cache.len());
+        assert_eq!(size + entry_size(&"hey", &"mercury"), cache.current_size());
+
+        cache.purge_expired();
+
         assert_eq!(5, cache.len());
-    
-        cache.insert("hey", "mercury").unwrap();
-        cache.insert("what's up", "saturn").unwrap();
-        cache.shrink_to_fit();
-    
-        assert!(cache.capacity() >= 7);
-        assert_eq!(7, cache.len());
+        assert_eq!(size, cache.current_size());
     }
 
     #[test]
-    fn shrinking_large_cache_decreases_capacity() {
-        let mut cache = LruCache::with_capacity(1024, 256);
-        cache.shrink_to(64);
-
-        assert!(cache.capacity() < 256);
-        assert!(cache.capacity() >= 64);
+    fn iterators_skip_expired_entries() {
+        let cache = large_test_cache_with_expired_entry();
+
+        assert_eq!(vec![(&"hello", &"world"), (&"greetings", &"moon"),
+            (&"ahoy", &"mars"), (&"hi", &"venus"),
+            (&"good morning", &"jupiter")], cache.iter().collect::<Vec<_>>());
+        assert_eq!(vec!["good morning", "hi", "ahoy", "greetings", "hello"],
+            cache.keys().rev().copied().collect::<Vec<_>>());
+        assert_eq!(vec!["world", "moon", "mars", "venus", "jupiter"],
+            cache.values().copied().collect::<Vec<_>>());
+        assert_eq!(vec!["ahoy", "hi", "good morning"],
+            cache.iter_from("ahoy").unwrap()
+                .map(|(k, _)| *k)
+                .collect::<Vec<_>>());
+        assert_eq!(vec!["hi", "ahoy", "greetings", "hello"],
+            cache.iter_from_back("hi").unwrap()
+                .rev()
+                .map(|(k, _)| *k)
+                .collect::<Vec<_>>());
+    }
 
-        cache.insert("hey", "mercury").unwrap();
-        cache.insert("what's up", "saturn").unwrap();
-        cache.shrink_to_fit();
+    #[test]
+    fn rank_of_does_not_count_expired_entries() {
+        let cache = large_test_cache_with_expired_entry();
+        let more_recent_size = entry_size(&"hi", &"venus") +
+            entry_size(&"good morning", &"jupiter");
 
-        assert!(cache.capacity() < 64);
-        assert!(cache.capacity() >= 2);
+        assert_eq!(Some((2, more_recent_size)), cache.rank_of("ahoy"));
+        assert_eq!(None, cache.rank_of("hey"));
     }
 
     #[test]
-    fn cache_created_with_capacity_does_not_reallocate_before_capacity_is_reached() {
-        let mut cache = LruCache::with_capacity(4096, 10);
-        let capacity_before = cache.capacity();
+    fn expiration_times_survive_reallocation() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        cache.reserve(100);
 
-        assert!(capacity_before >= 10);
+        assert_eq!(Some(&"mercury"), cache.peek("hey"));
 
-        for i in 0..10 {
-            cache.insert(i, "value").unwrap();
-        }
+        clock.advance(SECOND);
 
-        assert_eq!(capacity_before, cache.capacity());
+        assert_eq!(None, cache.peek("hey"));
+        assert_eq!(Some(&"world"), cache.peek("hello"));
     }
 
-    struct MockHasher {
-        hash_requests: Arc<Mutex<u32>>
+    #[test]
+    fn expired_entries_are_treated_as_absent_when_inserting() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        cache.insert_with_ttl("howdy", "saturn", SECOND).unwrap();
+        cache.insert_with_ttl("yo", "uranus", SECOND).unwrap();
+        clock.advance(SECOND);
+
+        assert_eq!(Ok(None), cache.insert("hey", "sun"));
+        assert_eq!(Ok(()), cache.try_insert("howdy", "moon"));
+        assert!(matches!(cache.entry("yo"), CacheEntry::Vacant(_)));
+        assert_eq!(vec![
+            ("hey", RemovalCause::Expired),
+            ("howdy", RemovalCause::Expired),
+            ("yo", RemovalCause::Expired)
+        ], *log.lock().unwrap());
     }
 
-    impl Hasher for MockHasher {
-        fn finish(&self) -> u64 {
-            *self.hash_requests.lock().unwrap() += 1;
-            0
-        }
-
-        fn write(&mut self, _bytes: &[u8]) { }
-    }
+    #[test]
+    fn removing_expired_entry_returns_none() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.insert_with_ttl("hey", "mercury", SECOND).unwrap();
+        clock.advance(SECOND);
 
-    struct MockBuildHasher {
-        hash_requests: Arc<Mutex<u32>>
+        assert_eq!(None, cache.remove("hey"));
+        assert_eq!(5, cache.len());
     }
 
-    impl BuildHasher for MockBuildHasher {
-        type Hasher = MockHasher;
+    #[test]
+    fn idle_time_is_reset_by_access_only() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(2 * SECOND));
+        clock.advance(SECOND);
+        cache.get("hello");
+        cache.touch("greetings");
+        cache.mutate("ahoy", |_| { }).unwrap();
+        cache.peek("hi");
+        clock.advance(SECOND);
 
-        fn build_hasher(&self) -> Self::Hasher {
-            MockHasher {
-                hash_requests: Arc::clone(&self.hash_requests)
-            }
-        }
+        assert!(cache.contains("hello"));
+        assert!(cache.contains("greetings"));
+        assert!(cache.contains("ahoy"));
+        assert!(!cache.contains("hi"));
+        assert!(!cache.contains("good morning"));
     }
 
     #[test]
-    fn cache_uses_given_hasher() {
-        let build_hasher = MockBuildHasher {
-            hash_requests: Arc::new(Mutex::new(0))
-        };
-        let mut cache = LruCache::with_hasher(1024, build_hasher);
+    fn enabling_time_to_idle_starts_idle_time_of_all_entries() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        clock.advance(SECOND);
+        cache.set_time_to_idle(Some(SECOND));
 
-        assert_eq!(0, *cache.hasher().hash_requests.lock().unwrap());
+        assert_eq!(5, cache.iter().filter(|(k, _)| cache.contains(*k)).count());
 
-        cache.insert("hello", "world").unwrap();
+        clock.advance(SECOND);
 
-        assert_eq!(1, *cache.hasher().hash_requests.lock().unwrap());
+        assert_eq!(0, cache.iter().filter(|(k, _)| cache.contains(*k)).count());
+    }
 
-        cache.insert("greetings", "moon").unwrap();
-        cache.insert("ahoy", "mars").unwrap();
+    #[test]
+    fn remove_idle_removes_from_lru_end_until_fresh_entry() {
+        let mut cache = large_test_cache();
+        let log = register_removal_log(&mut cache);
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(2 * SECOND));
+        clock.advance(SECOND);
+        cache.touch("greetings");
+        cache.touch("hi");
+        clock.advance(SECOND);
+        cache.remove_idle();
 
-        assert_eq!(3, *cache.hasher().hash_requests.lock().unwrap());
+        assert_eq!(2, cache.len());
+        assert_eq!(vec![
+            ("hello", RemovalCause::Expired),
+            ("ahoy", RemovalCause::Expired),
+            ("good morning", RemovalCause::Expired)
+        ], *log.lock().unwrap());
     }
 
     #[test]
-    fn clone_creates_independent_cache() {
+    fn cold_entry_starts_with_idle_time_of_previous_lru() {
         let mut cache = LruCache::new(1024);
-        cache.insert("hello", "world").unwrap();
-        cache.insert("greetings", "moon").unwrap();
-    
-        let mut clone = cache.clone();
-        clone.insert("ahoy", "mars").unwrap();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(10 * SECOND));
+        cache.insert(1, 1).unwrap();
+        clock.advance(5 * SECOND);
+        cache.insert(2, 2).unwrap();
+        cache.insert_cold(3, 3).unwrap();
+        clock.advance(5 * SECOND);
+        cache.remove_idle();
+
+        assert_eq!(vec![2], cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn remove_idle_skips_pinned_entries() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(SECOND));
+        cache.pin("hello");
+        clock.advance(SECOND);
+        cache.remove_idle();
 
-        cache.remove(&"hello");
-        cache.touch(&"greetings");
-    
         assert_eq!(1, cache.len());
-        assert_eq!(None, cache.get(&"hello"));
-        assert_eq!(Some(&"moon"), cache.get(&"greetings"));
-        assert_eq!(None, cache.get(&"ahoy"));
-    
-        assert_eq!(3, clone.len());
-        assert_eq!(Some(&"world"), clone.get(&"hello"));
-        assert_eq!(Some(&"moon"), clone.get(&"greetings"));
-        assert_eq!(Some(&"mars"), clone.get(&"ahoy"));
-    
-        assert!(clone.current_size() > cache.current_size());
+        assert!(cache.contains("hello"));
+    }
 
-        let cache_drained = cache.drain().collect::<Vec<_>>();
-        let cache_drained_expected = vec![("greetings", "moon")];
-        let clone_drained = clone.drain().collect::<Vec<_>>();
-        let clone_drained_expected = vec![
-            ("hello", "world"),
-            ("greetings", "moon"),
-            ("ahoy", "mars")
-        ];
+    #[test]
+    fn remove_idle_without_time_to_idle_does_nothing() {
+        let mut cache = large_test_cache();
+        cache.remove_idle();
 
-        assert_eq!(cache_drained_expected, cache_drained);
-        assert_eq!(clone_drained_expected, clone_drained);
+        assert_eq!(5, cache.len());
     }
-    
+
     #[test]
     fn touching_in_singleton_works() {
         // Note: This weirdly specific test case isolates a previous bug.
@@ -2365,6 +7127,114 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    fn demoting_sets_least_recently_used() {
+        let mut cache = large_test_cache();
+        cache.demote("hi");
+
+        assert_eq!(Some((&"hi", &"venus")), cache.peek_lru());
+        assert_eq!(5, cache.len());
+    }
+
+    #[test]
+    fn demoting_does_not_reset_idle_time() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(Duration::from_secs(10)));
+        clock.advance(Duration::from_secs(5));
+        cache.demote("hello");
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(None, cache.get("hello"));
+    }
+
+    #[test]
+    fn demoting_extends_idle_time_to_that_of_previous_lru() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(10 * SECOND));
+        clock.advance(5 * SECOND);
+        cache.touch("good morning");
+        cache.touch("hello");
+        cache.demote("good morning");
+        clock.advance(5 * SECOND);
+        cache.remove_idle();
+
+        assert_eq!(vec!["hello"], cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn moving_entry_towards_lru_extends_idle_time() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(10 * SECOND));
+        clock.advance(5 * SECOND);
+        cache.touch("hello");
+        cache.move_before("hello", "ahoy");
+        clock.advance(5 * SECOND);
+        cache.remove_idle();
+
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn moving_entry_towards_mru_shortens_idle_time() {
+        let mut cache = large_test_cache();
+        let clock = set_manual_clock(&mut cache);
+        cache.set_time_to_idle(Some(10 * SECOND));
+        clock.advance(5 * SECOND);
+        cache.touch("ahoy");
+        cache.touch("hi");
+        cache.move_before("hello", "hi");
+        clock.advance(5 * SECOND);
+        cache.remove_idle();
+
+        assert_eq!(vec!["ahoy", "hello", "hi"],
+            cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn insert_cold_inserts_as_least_recently_used() {
+        let mut cache = large_test_cache();
+        cache.insert_cold("bonjour", "saturn").unwrap();
+
+        assert_eq!(Some((&"bonjour", &"saturn")), cache.peek_lru());
+        assert_eq!(Some((&"good morning", &"jupiter")), cache.peek_mru());
+    }
+
+    #[test]
+    fn insert_cold_entry_is_ejected_first() {
+        let mut cache = large_test_cache();
+        cache.set_max_size(cache.current_size());
+        cache.insert_cold("bonjour", "earth").unwrap();
+        cache.insert("hey", "pluto").unwrap();
+
+        assert!(!cache.contains("bonjour"));
+        assert!(cache.contains("greetings"));
+    }
+
+    #[test]
+    fn move_before_places_entry_directly_before_other() {
+        let mut cache = large_test_cache();
+
+        assert!(cache.move_before("good morning", "greetings"));
+        assert_eq!(vec!["hello", "good morning", "greetings", "ahoy", "hi"],
+            cache.keys().copied().collect::<Vec<_>>());
+        assert!(cache.move_before("hello", "hi"));
+        assert_eq!(vec!["good morning", "greetings", "ahoy", "hello", "hi"],
+            cache.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn move_before_with_missing_or_equal_keys_does_nothing() {
+        let mut cache = large_test_cache();
+
+        assert!(!cache.move_before("hello", "bonjour"));
+        assert!(!cache.move_before("bonjour", "hello"));
+        assert!(!cache.move_before("hello", "hello"));
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+    }
+
     #[test]
     fn empty_cache_formats_for_debug_correctly() {
         let cache = LruCache::<&str, &str>::new(1024);
@@ -0,0 +1,53 @@
+/// An enumeration of the different reasons for which an entry can be removed
+/// from an [LruCache](crate::LruCache). This is reported to the removal
+/// listener registered with
+/// [LruCache::set_removal_listener](crate::LruCache::set_removal_listener).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RemovalCause {
+
+    /// The entry was ejected as least-recently-used to make room for a newly
+    /// inserted or expanded entry, or to free memory by dropping the iterator
+    /// returned by [LruCache::evict_to](crate::LruCache::evict_to) or
+    /// [LruCache::evict_bytes](crate::LruCache::evict_bytes) before it was
+    /// exhausted.
+    Ejected,
+
+    /// The entry was ejected as least-recently-used because the memory limit
+    /// was reduced using
+    /// [LruCache::set_max_size](crate::LruCache::set_max_size), or by dropping
+    /// the iterator returned by [LruCache::resize](crate::LruCache::resize)
+    /// before it was exhausted.
+    MaxSizeReduced,
+
+    /// The entry was replaced by an entry with an equal key when merging
+    /// caches using [LruCache::merge](crate::LruCache::merge).
+    Replaced,
+
+    /// The entry was expanded by a mutation beyond the memory limit of the
+    /// cache (see [MutateError::EntryTooLarge](crate::MutateError)) and the
+    /// error was not returned to the caller, e.g. when dropping the guard
+    /// returned by [LruCache::get_mut](crate::LruCache::get_mut).
+    EntryTooLarge,
+
+    /// The entry was removed by clearing the cache with
+    /// [LruCache::clear](crate::LruCache::clear) or by dropping the iterator
+    /// returned by [LruCache::drain](crate::LruCache::drain) before it was
+    /// exhausted.
+    Cleared,
+
+    /// The entry was rejected by the predicate given to
+    /// [LruCache::retain](crate::LruCache::retain).
+    Filtered,
+
+    /// The time-to-live of the entry elapsed (see
+    /// [LruCache::insert_with_ttl](crate::LruCache::insert_with_ttl)) or it
+    /// was idle for longer than the time-to-idle of the cache (see
+    /// [LruCache::set_time_to_idle](crate::LruCache::set_time_to_idle)).
+    /// Expired entries are removed lazily once they are accessed, replaced, or
+    /// purged using [LruCache::purge_expired](crate::LruCache::purge_expired)
+    /// or [LruCache::remove_idle](crate::LruCache::remove_idle).
+    Expired
+}
+
+pub(crate) type RemovalListener<K, V> =
+    Box<dyn FnMut(K, V, RemovalCause) + Send>;
@@ -0,0 +1,17 @@
+/// An enumeration of the rules by which
+/// [LruCache::merge](crate::LruCache::merge) resolves keys contained in both
+/// merged caches.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ConflictPolicy {
+
+    /// The entry of the cache into which the other one is merged is kept.
+    KeepExisting,
+
+    /// The entry of the cache which is merged into the other one is kept.
+    KeepIncoming,
+
+    /// The entry which was used more recently is kept. If neither was used
+    /// more recently than the other, the entry of the cache into which the
+    /// other one is merged is kept.
+    KeepMoreRecent
+}
@@ -0,0 +1,637 @@
+use crate::{DefaultWeigher, Equivalent, InsertError, LruCache, MutateError};
+use crate::{RemovalCause, Weigher};
+use crate::entry::{Entry, EntryPtr};
+
+use hashbrown::raw::Bucket;
+
+use std::hash::{BuildHasher, Hash};
+
+/// A builder for looking up entries of an [LruCache] by a precomputed hash.
+/// This is obtained by calling [LruCache::raw_entry]. Lookups through this
+/// builder do not alter the usage history, i.e. the semantics are as in
+/// [LruCache::peek].
+pub struct RawEntryBuilder<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a LruCache<K, V, S, W>
+}
+
+impl<'a, K, V, S, W> RawEntryBuilder<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+    pub(crate) fn new(cache: &'a LruCache<K, V, S, W>)
+            -> RawEntryBuilder<'a, K, V, S, W> {
+        RawEntryBuilder {
+            cache
+        }
+    }
+
+    /// Looks up the entry associated with the given key, hashing it with the
+    /// hasher of the cache. Returns references to the key and value of the
+    /// entry, or `None` if there is none.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry to search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.raw_entry().from_key("apple"));
+    /// assert_eq!(None, cache.raw_entry().from_key("lemon"));
+    /// ```
+    pub fn from_key<Q>(self, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let hash = self.cache.hash_key(key);
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    /// Looks up the entry associated with the given key, assuming the given
+    /// hash is the one computed by [LruCache::hash_key] for that key. Returns
+    /// references to the key and value of the entry, or `None` if there is
+    /// none. If the hash does not belong to the key, the entry may not be
+    /// found.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: The hash of `key`.
+    /// * `key`: The key of the entry to search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.raw_entry().from_key_hashed_nocheck(hash, "apple"));
+    /// ```
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q)
+        -> Option<(&'a K, &'a V)>
+    where
+        Q: Equivalent<K> + ?Sized
+    {
+        self.from_hash(hash, |k| key.equivalent(k))
+    }
+
+    /// Looks up an entry with the given hash whose key satisfies the given
+    /// predicate. Returns references to the key and value of the entry, or
+    /// `None` if there is none. Only entries whose keys hash to the given
+    /// hash according to [LruCache::hash_key] are found.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: The hash of the key to search.
+    /// * `is_match`: A predicate which decides whether a key that is stored
+    ///   in the cache with the given hash is the one searched for.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::LruCache;
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// assert_eq!(Some((&"apple".to_owned(), &"sweet".to_owned())),
+    ///     cache.raw_entry().from_hash(hash, |k| k == "apple"));
+    /// ```
+    pub fn from_hash<F>(self, hash: u64, mut is_match: F)
+        -> Option<(&'a K, &'a V)>
+    where
+        F: FnMut(&K) -> bool
+    {
+        let cache = self.cache;
+        let bucket =
+            cache.table.find(hash, |entry| is_match(unsafe { entry.key() }))?;
+
+        unsafe {
+            if cache.is_bucket_expired(&bucket) {
+                None
+            }
+            else {
+                let entry = bucket.as_ref();
+                Some((entry.key(), entry.value()))
+            }
+        }
+    }
+}
+
+/// A builder for looking up entries of an [LruCache] by a precomputed hash for
+/// in-place manipulation. This is obtained by calling
+/// [LruCache::raw_entry_mut].
+///
+/// As with [LruCache::entry], a found entry is marked as most-recently-used.
+/// If it is expired (see [LruCache::insert_with_ttl]), it is removed instead
+/// and a vacant entry is returned.
+pub struct RawEntryBuilderMut<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>
+}
+
+impl<'a, K, V, S, W> RawEntryBuilderMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher
+{
+    pub(crate) fn new(cache: &'a mut LruCache<K, V, S, W>)
+            -> RawEntryBuilderMut<'a, K, V, S, W> {
+        RawEntryBuilderMut {
+            cache
+        }
+    }
+
+    /// Gets the entry associated with the given key, hashing it with the
+    /// hasher of the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the entry to get.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// assert!(matches!(cache.raw_entry_mut().from_key("apple"),
+    ///     RawEntryMut::Occupied(_)));
+    /// assert!(matches!(cache.raw_entry_mut().from_key("lemon"),
+    ///     RawEntryMut::Vacant(_)));
+    /// ```
+    pub fn from_key<Q>(self, key: &Q) -> RawEntryMut<'a, K, V, S, W>
+    where
+        Q: Hash + Equivalent<K> + ?Sized
+    {
+        let hash = self.cache.hash_key(key);
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    /// Gets the entry associated with the given key, assuming the given hash
+    /// is the one computed by [LruCache::hash_key] for that key. If the hash
+    /// does not belong to the key, the entry may not be found, and inserting
+    /// into the returned vacant entry may store a second entry for the key,
+    /// which lookups by key do not reliably find.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: The hash of `key`.
+    /// * `key`: The key of the entry to get.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// if let RawEntryMut::Vacant(entry) =
+    ///         cache.raw_entry_mut().from_key_hashed_nocheck(hash, "apple") {
+    ///     entry.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// }
+    ///
+    /// assert_eq!(Some(&"sweet".to_owned()), cache.peek("apple"));
+    /// ```
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q)
+        -> RawEntryMut<'a, K, V, S, W>
+    where
+        Q: Equivalent<K> + ?Sized
+    {
+        self.from_hash(hash, |k| key.equivalent(k))
+    }
+
+    /// Gets an entry with the given hash whose key satisfies the given
+    /// predicate. Only entries whose keys hash to the given hash according to
+    /// [LruCache::hash_key] are found. Any key inserted into the returned
+    /// vacant entry must hash to the given hash as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: The hash of the key to get.
+    /// * `is_match`: A predicate which decides whether a key that is stored
+    ///   in the cache with the given hash is the one searched for.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    /// let hash = cache.hash_key("apple");
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_hash(hash, |k| k == "apple") {
+    ///     assert_eq!("sweet", entry.remove());
+    /// }
+    ///
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn from_hash<F>(self, hash: u64, mut is_match: F)
+        -> RawEntryMut<'a, K, V, S, W>
+    where
+        F: FnMut(&K) -> bool
+    {
+        let cache = self.cache;
+        let bucket =
+            cache.table.find(hash, |entry| is_match(unsafe { entry.key() }));
+
+        match bucket {
+            Some(bucket) if unsafe { cache.is_bucket_expired(&bucket) } => {
+                unsafe { cache.remove_bucket(bucket, RemovalCause::Expired); }
+                RawEntryMut::Vacant(RawVacantEntryMut::new(cache, hash))
+            },
+            Some(bucket) => {
+                cache.touch_ptr(EntryPtr::new(bucket.as_ptr()));
+                RawEntryMut::Occupied(RawOccupiedEntryMut::new(cache, bucket))
+            },
+            None => RawEntryMut::Vacant(RawVacantEntryMut::new(cache, hash))
+        }
+    }
+}
+
+/// A view into a single entry of an [LruCache] obtained by a precomputed
+/// hash, which may either be vacant or occupied. This is obtained from a
+/// [RawEntryBuilderMut].
+pub enum RawEntryMut<'a, K, V, S, W = DefaultWeigher> {
+
+    /// An occupied entry, i.e. the cache contains a matching entry.
+    Occupied(RawOccupiedEntryMut<'a, K, V, S, W>),
+
+    /// A vacant entry, i.e. the cache contains no matching entry.
+    Vacant(RawVacantEntryMut<'a, K, V, S, W>)
+}
+
+/// A view into an occupied entry of an [LruCache] obtained by a precomputed
+/// hash. This is part of the [RawEntryMut] enum.
+pub struct RawOccupiedEntryMut<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>,
+    bucket: Bucket<Entry<K, V>>
+}
+
+impl<'a, K, V, S, W> RawOccupiedEntryMut<'a, K, V, S, W> {
+    fn new(cache: &'a mut LruCache<K, V, S, W>, bucket: Bucket<Entry<K, V>>)
+            -> RawOccupiedEntryMut<'a, K, V, S, W> {
+        RawOccupiedEntryMut {
+            cache,
+            bucket
+        }
+    }
+
+    fn entry_ptr(&self) -> EntryPtr<K, V> {
+        EntryPtr::new(self.bucket.as_ptr())
+    }
+
+    /// Gets a reference to the key stored in the cache for this entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     assert_eq!("apple", entry.key());
+    /// }
+    /// ```
+    pub fn key(&self) -> &K {
+        unsafe { self.bucket.as_ref().key() }
+    }
+
+    /// Gets a reference to the value of this entry.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     assert_eq!("sweet", entry.get());
+    /// }
+    /// ```
+    pub fn get(&self) -> &V {
+        unsafe { self.bucket.as_ref().value() }
+    }
+
+    /// Converts this entry into references to its key and value with a
+    /// lifetime bound to the cache itself.
+    ///
+    /// The memory requirement of the value may not be changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     assert_eq!((&"apple".to_owned(), &"sweet".to_owned()),
+    ///         entry.into_key_value());
+    /// }
+    /// ```
+    pub fn into_key_value(self) -> (&'a K, &'a V) {
+        let entry = unsafe { self.entry_ptr().get_extended() };
+
+        unsafe { (entry.key(), entry.value()) }
+    }
+}
+
+impl<'a, K, V, S, W> RawOccupiedEntryMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+
+    /// Applies a mutating function to the value of this entry. As with
+    /// [LruCache::mutate], the size of the entry is updated after the
+    /// operation and, if necessary, least-recently-used entries are ejected to
+    /// restore the memory requirement.
+    ///
+    /// # Arguments
+    ///
+    /// * `op`: An operation that takes as input a mutable reference to the
+    ///   value and mutates it. Its result is returned.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [MutateError::EntryTooLarge] if the operation expanded the
+    /// value so much that the entry no longer fit inside the memory limit of
+    /// the cache, or an [MutateError::WouldEjectPinned] if it no longer fit
+    /// beside the other pinned entries (see [LruCache::pin]). If that is the
+    /// case, the entry is removed and its parts returned in the error data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     entry.mutate(|s| s.push_str(" and sour")).unwrap();
+    /// }
+    ///
+    /// assert_eq!(Some(&"sweet and sour".to_owned()), cache.peek("apple"));
+    /// ```
+    pub fn mutate<R, F>(self, op: F) -> Result<R, MutateError<K, V>>
+    where
+        F: FnOnce(&mut V) -> R
+    {
+        let entry_ptr = self.entry_ptr();
+
+        unsafe { self.cache.mutate_ptr(entry_ptr, op, LruCache::drop_ejected) }
+    }
+
+    /// Removes this entry from the cache and returns its key and value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     assert_eq!(("apple".to_owned(), "sweet".to_owned()),
+    ///         entry.remove_entry());
+    /// }
+    ///
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.cache.take_bucket(self.bucket) }
+    }
+
+    /// Removes this entry from the cache and returns its value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    /// cache.insert("apple".to_owned(), "sweet".to_owned()).unwrap();
+    ///
+    /// if let RawEntryMut::Occupied(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     assert_eq!("sweet".to_owned(), entry.remove());
+    /// }
+    ///
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+/// A view into a vacant entry of an [LruCache] obtained by a precomputed hash.
+/// This is part of the [RawEntryMut] enum.
+pub struct RawVacantEntryMut<'a, K, V, S, W = DefaultWeigher> {
+    cache: &'a mut LruCache<K, V, S, W>,
+    hash: u64
+}
+
+impl<'a, K, V, S, W> RawVacantEntryMut<'a, K, V, S, W> {
+    fn new(cache: &'a mut LruCache<K, V, S, W>, hash: u64)
+            -> RawVacantEntryMut<'a, K, V, S, W> {
+        RawVacantEntryMut {
+            cache,
+            hash
+        }
+    }
+}
+
+impl<'a, K, V, S, W> RawVacantEntryMut<'a, K, V, S, W>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    W: Weigher<K, V>
+{
+
+    /// Inserts the given key and value into the cache using the hash this
+    /// entry was obtained with, which must be the hash of the given key
+    /// according to [LruCache::hash_key]. The new entry is
+    /// most-recently-used. If inserting it would violate the memory limit, the
+    /// least-recently-used values are ejected from the cache until it fits.
+    /// References to the inserted key and value are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the inserted entry.
+    /// * `value`: The value to store in the inserted entry.
+    ///
+    /// # Errors
+    ///
+    /// Raises an [InsertError::EntryTooLarge] if the entry alone would already
+    /// be too large to fit inside the cache's size limit, or an
+    /// [InsertError::WouldEjectPinned] if it does not fit beside the pinned
+    /// entries (see [LruCache::pin]). If this occurs, the entry was not
+    /// inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lru_mem::{LruCache, RawEntryMut};
+    ///
+    /// let mut cache = LruCache::new(1024);
+    ///
+    /// if let RawEntryMut::Vacant(entry) =
+    ///         cache.raw_entry_mut().from_key("apple") {
+    ///     assert_eq!(Ok((&"apple".to_owned(), &"sweet".to_owned())),
+    ///         entry.insert("apple".to_owned(), "sweet".to_owned()));
+    /// }
+    ///
+    /// assert_eq!(1, cache.len());
+    /// ```
+    pub fn insert(self, key: K, value: V)
+        -> Result<(&'a K, &'a V), InsertError<K, V>> {
+        let entry = self.cache.prepare_insert(key, value)?;
+        let available_memory = self.cache.available_memory();
+        let entry = self.cache.check_available_memory(entry, available_memory)?;
+        self.cache.eject_to_target(self.cache.max_size - entry.size(),
+            RemovalCause::Ejected);
+        let entry_ptr = self.cache.insert_unchecked(entry, self.hash,
+            self.cache.default_ttl);
+        let entry = unsafe { entry_ptr.get_extended() };
+
+        unsafe { Ok((entry.key(), entry.value())) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{LruCache, MutateError, RawEntryMut};
+    use crate::tests::large_test_cache;
+
+    #[test]
+    fn raw_entry_lookup_does_not_alter_usage_history() {
+        let cache = large_test_cache();
+        let hash = cache.hash_key("hello");
+
+        assert_eq!(Some((&"hello", &"world")),
+            cache.raw_entry().from_key_hashed_nocheck(hash, "hello"));
+        assert_eq!(None, cache.raw_entry().from_hash(hash, |k| *k == "hi"));
+        assert_eq!(Some((&"hello", &"world")), cache.peek_lru());
+    }
+
+    #[test]
+    fn raw_entry_mut_occupied_is_marked_most_recently_used() {
+        let mut cache = large_test_cache();
+        let hash = cache.hash_key("hello");
+        let entry =
+            cache.raw_entry_mut().from_key_hashed_nocheck(hash, "hello");
+
+        assert!(matches!(entry, RawEntryMut::Occupied(_)));
+        assert_eq!(Some((&"hello", &"world")), cache.peek_mru());
+    }
+
+    #[test]
+    fn raw_vacant_insert_can_be_found_by_hash() {
+        let mut cache = large_test_cache();
+        let hash = cache.hash_key("bonjour");
+
+        if let RawEntryMut::Vacant(entry) =
+                cache.raw_entry_mut().from_hash(hash, |k| *k == "bonjour") {
+            entry.insert("bonjour", "saturn").unwrap();
+        }
+        else {
+            panic!("entry was occupied");
+        }
+
+        assert_eq!(6, cache.len());
+        assert_eq!(Some(&"saturn"), cache.peek("bonjour"));
+        assert_eq!(Some((&"bonjour", &"saturn")), cache.peek_mru());
+    }
+
+    #[test]
+    fn raw_vacant_insert_survives_reallocation() {
+        let mut cache = LruCache::new(65536);
+
+        for key in 0..64u32 {
+            let hash = cache.hash_key(&key);
+
+            if let RawEntryMut::Vacant(entry) =
+                    cache.raw_entry_mut().from_key_hashed_nocheck(hash, &key) {
+                entry.insert(key, key).unwrap();
+            }
+        }
+
+        for key in 0..64u32 {
+            assert_eq!(Some(&key), cache.peek(&key));
+        }
+    }
+
+    #[test]
+    fn duplicate_key_inserted_by_hash_is_removed_independently() {
+        let mut cache = LruCache::new(1024);
+        cache.insert("hello".to_owned(), "world".to_owned()).unwrap();
+        let hash = cache.hash_key("hello");
+
+        if let RawEntryMut::Vacant(entry) =
+                cache.raw_entry_mut().from_hash(hash, |_| false) {
+            entry.insert("hello".to_owned(), "moon".to_owned()).unwrap();
+        }
+        else {
+            panic!("entry was occupied");
+        }
+
+        assert_eq!(2, cache.len());
+
+        cache.touch("hello");
+        cache.set_max_size(0);
+
+        assert!(cache.is_empty());
+        assert_eq!(0, cache.current_size());
+        assert_eq!(None, cache.peek("hello"));
+    }
+
+    #[test]
+    fn raw_occupied_mutate_removes_too_expanded_entry() {
+        let mut cache = LruCache::new(1024);
+        cache.insert(0, vec![0u8; 8]).unwrap();
+        cache.insert(1, vec![0u8; 8]).unwrap();
+
+        if let RawEntryMut::Occupied(entry) =
+                cache.raw_entry_mut().from_key(&0) {
+            let result = entry.mutate(|v| v.resize(1024, 0));
+
+            assert!(matches!(result,
+                Err(MutateError::EntryTooLarge { key: 0, .. })));
+        }
+
+        assert_eq!(1, cache.len());
+        assert!(!cache.contains(&0));
+    }
+}
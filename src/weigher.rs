@@ -0,0 +1,129 @@
+use crate::{entry_size, MemSize};
+
+/// Determines the weight of the entries of an [LruCache](crate::LruCache),
+/// that is, how much of the cache's maximum size they occupy. By default, the
+/// [DefaultWeigher] is used, which estimates the memory requirement of each
+/// entry using [entry_size]. A custom weigher can be provided with
+/// [LruCache::with_weigher](crate::LruCache::with_weigher), which is useful if
+/// the cost of an entry is not its memory, or if the key or value type does
+/// not implement [MemSize].
+///
+/// The weight of an entry must only depend on its key and value. Whenever a
+/// value is mutated through the cache, its entry is weighed anew, unless the
+/// size is managed explicitly with methods such as
+/// [LruCache::insert_with_size](crate::LruCache::insert_with_size). Any
+/// function or closure taking references to a key and value and returning a
+/// `usize` is a weigher.
+///
+/// # Example
+///
+/// ```
+/// use lru_mem::Weigher;
+///
+/// struct MappedRegion {
+///     address: usize,
+///     len: usize
+/// }
+///
+/// struct RegionLength;
+///
+/// impl Weigher<u64, MappedRegion> for RegionLength {
+///     fn weigh(&self, _key: &u64, value: &MappedRegion) -> usize {
+///         value.len
+///     }
+/// }
+/// ```
+pub trait Weigher<K, V> {
+
+    /// Computes the weight of the entry with the given key and value.
+    fn weigh(&self, key: &K, value: &V) -> usize;
+}
+
+impl<K, V, F> Weigher<K, V> for F
+where
+    F: Fn(&K, &V) -> usize
+{
+    fn weigh(&self, key: &K, value: &V) -> usize {
+        self(key, value)
+    }
+}
+
+/// A [Weigher] that weighs entries by their estimated memory requirement in
+/// bytes as computed by [entry_size]. This is the default weigher of an
+/// [LruCache](crate::LruCache).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultWeigher;
+
+impl<K: MemSize, V: MemSize> Weigher<K, V> for DefaultWeigher {
+    fn weigh(&self, key: &K, value: &V) -> usize {
+        entry_size(key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hashbrown::hash_map::DefaultHashBuilder;
+
+    use crate::{LruCache, MutateError, TryInsertError, Weigher};
+
+    // Deliberately does not implement MemSize.
+    #[derive(Debug, PartialEq)]
+    struct Handle {
+        len: usize
+    }
+
+    fn handle_cache(max_weight: usize) -> LruCache<u32, Handle,
+            DefaultHashBuilder, impl Weigher<u32, Handle>> {
+        LruCache::with_weigher(max_weight, |_: &u32, h: &Handle| h.len)
+    }
+
+    #[test]
+    fn insert_uses_weigher() {
+        let mut cache = handle_cache(10);
+        cache.insert(0, Handle { len: 4 }).unwrap();
+        cache.insert(1, Handle { len: 4 }).unwrap();
+        cache.insert(2, Handle { len: 4 }).unwrap();
+
+        assert_eq!(vec![1, 2], cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(8, cache.current_size());
+    }
+
+    #[test]
+    fn try_insert_uses_weigher() {
+        let mut cache = handle_cache(10);
+        cache.try_insert(0, Handle { len: 6 }).unwrap();
+        let result = cache.try_insert(1, Handle { len: 6 });
+
+        assert!(matches!(result,
+            Err(TryInsertError::WouldEjectLru { entry_size: 6, .. })));
+        assert_eq!(6, cache.current_size());
+    }
+
+    #[test]
+    fn mutate_weighs_entry_anew() {
+        let mut cache = handle_cache(10);
+        cache.insert(0, Handle { len: 4 }).unwrap();
+        cache.insert(1, Handle { len: 4 }).unwrap();
+        cache.mutate(&1, |h| h.len = 7).unwrap();
+
+        assert_eq!(vec![1], cache.keys().copied().collect::<Vec<_>>());
+        assert_eq!(7, cache.current_size());
+
+        let result = cache.mutate(&1, |h| h.len = 11);
+
+        assert!(matches!(result,
+            Err(MutateError::EntryTooLarge { new_entry_size: 11, .. })));
+        assert!(cache.is_empty());
+        assert_eq!(0, cache.current_size());
+    }
+
+    #[test]
+    fn value_guard_weighs_entry_anew() {
+        let mut cache = handle_cache(10);
+        cache.insert(0, Handle { len: 4 }).unwrap();
+        cache.get_mut(&0).unwrap().len = 2;
+
+        assert_eq!(2, cache.current_size());
+    }
+}